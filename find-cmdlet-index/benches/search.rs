@@ -0,0 +1,164 @@
+//! Queries per second against a synthetic index, comparing a reader created
+//! for every query with one shared between queries.
+//!
+//! Run with `cargo bench`, optionally passing the number of cmdlets to index.
+
+use find_cmdlet_index::{
+    cmdlet::{Cmdlet, Example, ModuleInfo, Parameter, Source},
+    pascal_splitter,
+    schema::CmdletSchema,
+};
+use std::time::{Duration, Instant};
+
+const VERBS: &[&str] = &[
+    "Get", "Set", "New", "Remove", "Add", "Clear", "Start", "Stop", "Enable", "Disable",
+];
+const NOUNS: &[&str] = &[
+    "User",
+    "Group",
+    "Computer",
+    "Service",
+    "Process",
+    "Item",
+    "Content",
+    "Member",
+    "Policy",
+    "Certificate",
+    "Disk",
+    "Volume",
+    "Network",
+    "Adapter",
+    "Firewall",
+    "Rule",
+    "Mailbox",
+    "Database",
+    "Job",
+    "Task",
+];
+const QUERIES: &[&str] = &[
+    "user",
+    "getuser",
+    "group member",
+    "firewall rule",
+    "disk volume",
+    "certificate",
+    "stop service",
+    "mailbox database",
+    "process job",
+    "adapter",
+];
+const DURATION: Duration = Duration::from_secs(3);
+
+fn synthetic_cmdlet(i: usize) -> Cmdlet {
+    let verb = VERBS[i % VERBS.len()];
+    let noun = NOUNS[(i / VERBS.len()) % NOUNS.len()];
+    let noun2 = NOUNS[(i / (VERBS.len() * NOUNS.len())) % NOUNS.len()];
+    let module = format!("Module{}", i / 50);
+    let name = format!("{}-{}{}{}", verb, noun, noun2, i);
+
+    Cmdlet {
+        module: module.clone(),
+        module_version: "1.0.0".to_string(),
+        name: name.clone(),
+        aliases: Vec::new(),
+        url: format!("https://example.com/{}", name),
+        tags: vec![noun.to_lowercase(), module.to_lowercase()],
+        synopsis: format!("{}s a {} {}", verb, noun, noun2),
+        syntax: format!("{} [-Identity] <{}> [-Filter <String>]", name, noun),
+        description: format!(
+            "The {} cmdlet {}s {} objects in {}.",
+            name,
+            verb.to_lowercase(),
+            noun.to_lowercase(),
+            noun2.to_lowercase()
+        ),
+        notes: String::new(),
+        parameters: vec![Parameter {
+            name: "Identity".to_string(),
+            type_name: noun.to_string(),
+            description: format!("The {} to {}.", noun, verb.to_lowercase()),
+            ..Default::default()
+        }],
+        examples: vec![Example {
+            title: "Example 1".to_string(),
+            code: format!("PS C:\\> {} -Identity test", name),
+            remarks: format!("{}s the test {}.", verb, noun),
+        }],
+        links: Vec::new(),
+        module_info: ModuleInfo::default(),
+        source: Source::PSGallery,
+    }
+}
+
+fn build_index(cmdlets: usize) -> tantivy::Result<tantivy::Index> {
+    let schema = CmdletSchema::new();
+    let index = tantivy::Index::create_in_ram(schema.schema());
+    pascal_splitter::register(&index);
+
+    let mut writer = index.writer(200_000_000)?;
+    for i in 0..cmdlets {
+        writer.add_document(
+            schema
+                .to_document(&synthetic_cmdlet(i))
+                .expect("synthetic cmdlets are valid"),
+        );
+    }
+    writer.commit()?;
+
+    Ok(index)
+}
+
+fn search(
+    searcher: &tantivy::Searcher,
+    query_parser: &tantivy::query::QueryParser,
+    query_str: &str,
+) -> tantivy::Result<usize> {
+    let query = query_parser
+        .parse_query(query_str)
+        .expect("benchmark queries are valid");
+    let docs = searcher.search(&query, &tantivy::collector::TopDocs::with_limit(30))?;
+
+    Ok(docs.len())
+}
+
+/// Run queries for a fixed time, returning queries per second
+fn queries_per_second(mut run_query: impl FnMut(&str) -> tantivy::Result<usize>) -> f64 {
+    let start = Instant::now();
+    let mut queries = 0u64;
+    while start.elapsed() < DURATION {
+        for query_str in QUERIES {
+            run_query(query_str).expect("search failed");
+            queries += 1;
+        }
+    }
+
+    queries as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() -> tantivy::Result<()> {
+    // cargo bench passes --bench, so take the first numeric argument
+    let cmdlets = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(20_000);
+
+    let start = Instant::now();
+    let index = build_index(cmdlets)?;
+    println!("indexed {} cmdlets in {:?}", cmdlets, start.elapsed());
+
+    let schema = CmdletSchema::for_index(&index).expect("index was built with the schema");
+    let query_parser = tantivy::query::QueryParser::for_index(&index, schema.query_fields());
+
+    let per_query = queries_per_second(|query_str| {
+        let reader = index.reader()?;
+        search(&reader.searcher(), &query_parser, query_str)
+    });
+    println!("reader per query: {:>10.0} queries/s", per_query);
+
+    let reader = index.reader()?;
+    let shared =
+        queries_per_second(|query_str| search(&reader.searcher(), &query_parser, query_str));
+    println!("shared reader:    {:>10.0} queries/s", shared);
+
+    Ok(())
+}
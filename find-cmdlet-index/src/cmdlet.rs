@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+
+/// Cmdlet information, as stored in the index
+#[derive(Debug, PartialEq)]
+pub struct Cmdlet {
+    /// Module the cmdlet came from
+    pub module: String,
+
+    /// Module version for the cmdlet
+    pub module_version: String,
+
+    /// Full name of the cmdlet, eg. Get-Something
+    pub name: String,
+
+    /// Aliases which resolve to the cmdlet, eg. gci for Get-ChildItem
+    pub aliases: Vec<String>,
+
+    /// Help URL for the cmdlet
+    pub url: String,
+
+    /// List of tags to associate with the cmdlet
+    pub tags: Vec<String>,
+
+    /// Synopsis help text for the cmdlet
+    pub synopsis: String,
+
+    /// Syntax string for the cmdlet
+    pub syntax: String,
+
+    /// Full description help text for the cmdlet
+    pub description: String,
+
+    /// Help text notes for the cmdlet
+    pub notes: String,
+
+    /// Parameters accepted by the cmdlet, excluding common parameters
+    pub parameters: Vec<Parameter>,
+
+    /// Usage examples from the help text
+    pub examples: Vec<Example>,
+
+    /// Related links from the help text
+    pub links: Vec<Link>,
+
+    /// Information about the module the cmdlet came from
+    pub module_info: ModuleInfo,
+
+    /// Where the cmdlet's help was collected from
+    pub source: Source,
+}
+
+impl Cmdlet {
+    /// PowerShell verb for the cmdlet, eg. Get for Get-Something
+    pub fn verb(&self) -> Option<&str> {
+        let idx = self.name.find('-')?;
+        let verb = self.name[..idx].trim();
+        if verb.is_empty() {
+            None
+        } else {
+            Some(verb)
+        }
+    }
+}
+
+/// Where cmdlet help was collected from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Source {
+    /// Snap-ins built in to Windows PowerShell
+    Builtin,
+
+    /// Modules installed with Windows features, eg. RSAT
+    Rsat,
+
+    /// Modules from the PowerShell Gallery
+    PSGallery,
+
+    /// PlatyPS markdown help
+    Markdown,
+
+    /// MAML XML help files shipped with modules
+    Maml,
+}
+
+impl Source {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Builtin => "builtin",
+            Source::Rsat => "rsat",
+            Source::PSGallery => "psgallery",
+            Source::Markdown => "markdown",
+            Source::Maml => "maml",
+        }
+    }
+
+    pub fn parse(source: &str) -> Option<Source> {
+        match source {
+            "builtin" => Some(Source::Builtin),
+            "rsat" => Some(Source::Rsat),
+            "psgallery" => Some(Source::PSGallery),
+            "markdown" => Some(Source::Markdown),
+            "maml" => Some(Source::Maml),
+            _ => None,
+        }
+    }
+}
+
+/// Module information stored alongside each cmdlet
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ModuleInfo {
+    /// Author of the module
+    pub author: String,
+
+    /// Description of the module
+    pub description: String,
+
+    /// Project URL for the module
+    pub project_uri: String,
+
+    /// Date the module version was published, as YYYY-MM-DD
+    pub published: String,
+
+    /// GUID identifying the module, from its manifest
+    pub guid: String,
+
+    /// Company or vendor of the module
+    pub company_name: String,
+
+    /// License URL for the module
+    pub license_uri: String,
+
+    /// Minimum PowerShell version the module requires, eg. 5.1
+    pub powershell_version: String,
+
+    /// PowerShell editions the module supports, Desktop and/or Core
+    pub compatible_editions: Vec<String>,
+
+    /// Names of modules that must be imported before the module
+    pub required_modules: Vec<String>,
+}
+
+/// Parameter information for indexing
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Parameter {
+    /// Parameter name, without the leading dash
+    pub name: String,
+
+    /// Type name of the parameter, eg. System.String[]
+    pub type_name: String,
+
+    /// Alternative names for the parameter
+    pub aliases: Vec<String>,
+
+    /// Whether the parameter is mandatory in any parameter set
+    pub mandatory: bool,
+
+    /// Whether the parameter accepts pipeline input by value
+    pub pipeline_by_value: bool,
+
+    /// Whether the parameter accepts pipeline input by property name
+    pub pipeline_by_property_name: bool,
+
+    /// Parameter sets the parameter belongs to, empty for all parameter sets
+    pub parameter_sets: Vec<String>,
+
+    /// Help text for the parameter
+    pub description: String,
+}
+
+impl Parameter {
+    /// Parse pipeline input as written in help text, eg.
+    /// True (ByPropertyName, ByValue)
+    pub fn set_pipeline_input(&mut self, pipeline_input: &str) {
+        let pipeline_input = pipeline_input.to_ascii_lowercase();
+        if !pipeline_input.trim_start().starts_with("true") {
+            return;
+        }
+
+        self.pipeline_by_value |= pipeline_input.contains("byvalue");
+        self.pipeline_by_property_name |= pipeline_input.contains("bypropertyname");
+        if !self.pipeline_by_value && !self.pipeline_by_property_name {
+            self.pipeline_by_value = true;
+        }
+    }
+
+    /// Parse aliases as written in help text, eg. cn, host or None
+    pub fn add_aliases(&mut self, aliases: &str) {
+        for alias in aliases.split(',').map(str::trim) {
+            if alias.is_empty() || alias.eq_ignore_ascii_case("none") {
+                continue;
+            }
+            if !self.aliases.iter().any(|a| a.eq_ignore_ascii_case(alias)) {
+                self.aliases.push(alias.to_string());
+            }
+        }
+    }
+}
+
+/// Help text example for indexing
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Example {
+    /// Title of the example, eg. Example 1: Get a thing
+    pub title: String,
+
+    /// Example code
+    pub code: String,
+
+    /// Text explaining the example
+    pub remarks: String,
+}
+
+/// Related link from the help text
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Link {
+    /// Text of the link, eg. Get-Something
+    pub title: String,
+
+    /// URL of the link, empty for links to other cmdlets by name
+    pub uri: String,
+}
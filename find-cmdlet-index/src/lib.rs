@@ -1 +1,4 @@
-pub mod pascal_splitter;
\ No newline at end of file
+pub mod cmdlet;
+pub mod metadata;
+pub mod pascal_splitter;
+pub mod schema;
@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Version of the index layout, bumped whenever fields are added, removed or
+/// change how they're indexed. Indexes built with a different version need to
+/// be rebuilt.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// File in the index directory the metadata is written to
+pub const METADATA_FILE: &str = "find-cmdlet.json";
+
+#[derive(Debug)]
+pub enum MetadataError {
+    Missing,
+    Io(io::Error),
+    InvalidJson(serde_json::Error),
+    IncompatibleVersion(u32),
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataError::Missing => write!(
+                f,
+                "index has no {}, it was built before schema versioning",
+                METADATA_FILE
+            ),
+            MetadataError::Io(e) => write!(f, "could not access {}: {}", METADATA_FILE, e),
+            MetadataError::InvalidJson(e) => write!(f, "invalid json in {}: {}", METADATA_FILE, e),
+            MetadataError::IncompatibleVersion(version) => write!(
+                f,
+                "index has schema version {}, expected {}",
+                version, SCHEMA_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {}
+
+/// Information about how an index was built, stored alongside it
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct IndexMetadata {
+    pub schema_version: u32,
+
+    /// Version of the indexer which built the index
+    pub indexer_version: String,
+
+    pub built_at: DateTime<Utc>,
+
+    /// Number of cmdlets in the index
+    pub cmdlets: u64,
+
+    /// Number of distinct modules in the index
+    pub modules: u64,
+
+    /// Number of cmdlets from each source, eg. psgallery
+    pub sources: BTreeMap<String, u64>,
+}
+
+impl IndexMetadata {
+    /// Read the metadata from an index directory
+    pub fn read(directory: impl AsRef<Path>) -> Result<IndexMetadata, MetadataError> {
+        let file = match File::open(directory.as_ref().join(METADATA_FILE)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(MetadataError::Missing),
+            Err(e) => return Err(MetadataError::Io(e)),
+        };
+
+        serde_json::from_reader(io::BufReader::new(file)).map_err(MetadataError::InvalidJson)
+    }
+
+    /// Write the metadata to an index directory, replacing any existing metadata
+    pub fn write(&self, directory: impl AsRef<Path>) -> Result<(), MetadataError> {
+        let path = directory.as_ref().join(METADATA_FILE);
+        // Write then rename, so a running server never sees a partial file
+        let tmp_path = path.with_extension("json.tmp");
+        let file = File::create(&tmp_path).map_err(MetadataError::Io)?;
+        serde_json::to_writer_pretty(io::BufWriter::new(file), self)
+            .map_err(MetadataError::InvalidJson)?;
+
+        std::fs::rename(&tmp_path, &path).map_err(MetadataError::Io)
+    }
+
+    /// Check the index was built with the schema this version expects
+    pub fn check_compatible(&self) -> Result<(), MetadataError> {
+        if self.schema_version == SCHEMA_VERSION {
+            Ok(())
+        } else {
+            Err(MetadataError::IncompatibleVersion(self.schema_version))
+        }
+    }
+}
+
+#[test]
+fn metadata_round_trip() {
+    let directory =
+        std::env::temp_dir().join(format!("find-cmdlet-metadata-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    assert!(matches!(
+        IndexMetadata::read(&directory),
+        Err(MetadataError::Missing)
+    ));
+
+    let mut sources = BTreeMap::new();
+    sources.insert("psgallery".to_string(), 3);
+    let metadata = IndexMetadata {
+        schema_version: SCHEMA_VERSION,
+        indexer_version: "0.1.0".to_string(),
+        built_at: Utc::now(),
+        cmdlets: 3,
+        modules: 1,
+        sources,
+    };
+    metadata.write(&directory).unwrap();
+    let read = IndexMetadata::read(&directory).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(metadata, read);
+    assert!(read.check_compatible().is_ok());
+    assert!(IndexMetadata {
+        schema_version: SCHEMA_VERSION + 1,
+        ..read
+    }
+    .check_compatible()
+    .is_err());
+}
@@ -1,5 +1,5 @@
-use tantivy::Index;
 use tantivy::tokenizer::{BoxTokenStream, Token, TokenFilter, TokenStream};
+use tantivy::Index;
 use voca_rs::Voca;
 
 struct PascalSplitterStream<'a> {
@@ -83,15 +83,32 @@ impl TokenFilter for PascalSplitter {
     }
 }
 
+fn pascal_tokenizer() -> tantivy::tokenizer::TextAnalyzer {
+    tantivy::tokenizer::TextAnalyzer::from(tantivy::tokenizer::SimpleTokenizer)
+        .filter(PascalSplitter)
+        .filter(tantivy::tokenizer::LowerCaser)
+}
+
 pub fn register(index: &Index) {
-    let pascal_tokenizer =
-        tantivy::tokenizer::TextAnalyzer::from(tantivy::tokenizer::SimpleTokenizer)
-            .filter(PascalSplitter)
-            .filter(tantivy::tokenizer::LowerCaser);
-
-    index
-        .tokenizers()
-        .register("pascal", pascal_tokenizer);
+    index.tokenizers().register("pascal", pascal_tokenizer());
+}
+
+/// Lowercased suffixes of the text starting at each word, eg. Get-ADUser
+/// gives get-aduser, aduser and user. Indexing these allows prefix matching
+/// from the start of any word.
+pub fn word_suffixes(text: &str) -> Vec<String> {
+    let mut suffixes = Vec::new();
+    let mut stream = pascal_tokenizer().token_stream(text);
+    while stream.advance() {
+        if let Some(suffix) = text.get(stream.token().offset_from..) {
+            let suffix = suffix.to_lowercase();
+            if !suffixes.contains(&suffix) {
+                suffixes.push(suffix);
+            }
+        }
+    }
+
+    suffixes
 }
 
 #[test]
@@ -127,3 +144,12 @@ fn cmdlet_split() {
         }
     }
 }
+
+#[test]
+fn cmdlet_word_suffixes() {
+    assert_eq!(
+        word_suffixes("Get-ADUser"),
+        vec!["get-aduser", "aduser", "user"]
+    );
+    assert_eq!(word_suffixes("New-VM"), vec!["new-vm", "vm"]);
+}
@@ -0,0 +1,420 @@
+use crate::cmdlet::{Cmdlet, Source};
+use crate::pascal_splitter;
+use std::fmt;
+use tantivy::schema::{
+    Document, Facet, Field, IndexRecordOption, Schema, SchemaBuilder, TextFieldIndexing,
+    TextOptions, Value, STORED, STRING, TEXT,
+};
+use tantivy::{doc, Index, Term};
+
+/// Facets cmdlets can be filtered by
+pub const FACETS: &[&str] = &["module", "tag", "verb", "source"];
+
+/// Separates a suffix from the name it came from in name_suffix terms
+const NAME_SUFFIX_SEPARATOR: char = '\u{0}';
+
+/// Term identifying a single version of a module
+pub fn module_key(module: &str, version: &str) -> String {
+    format!("{}@{}", module.to_lowercase(), version.to_lowercase())
+}
+
+/// Facet for a value, eg. /tag/azure. Tags are written with inconsistent case
+/// across modules, so are lowercased.
+pub fn facet(facet: &str, value: &str) -> Facet {
+    let value = if facet == "tag" {
+        value.to_lowercase()
+    } else {
+        value.to_string()
+    };
+
+    Facet::from_path(vec![facet.to_string(), value])
+}
+
+/// Name a name_suffix term came from, in lowercase
+pub fn suffix_term_name(term: &str) -> Option<&str> {
+    let idx = term.find(NAME_SUFFIX_SEPARATOR)?;
+
+    Some(&term[idx + NAME_SUFFIX_SEPARATOR.len_utf8()..])
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    MissingField(&'static str),
+    MissingValue(&'static str),
+    InvalidJson(&'static str, serde_json::Error),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::MissingField(field) => write!(f, "missing field in index: {}", field),
+            SchemaError::MissingValue(field) => write!(f, "missing value for field: {}", field),
+            SchemaError::InvalidJson(field, e) => {
+                write!(f, "invalid json for field {}: {}", field, e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Schema for the cmdlet index, with handles for each field
+#[derive(Clone)]
+pub struct CmdletSchema {
+    schema: Schema,
+
+    pub module_name: Field,
+    pub module_version: Field,
+    /// Lowercase module name, for finding every version of a module
+    pub module_id: Field,
+    /// See `module_key`
+    pub module_key: Field,
+    pub name: Field,
+    /// Lowercase name, for completing names
+    pub name_lower: Field,
+    /// Suffixes of the name starting at each word, for completing names
+    pub name_suffix: Field,
+    pub aliases: Field,
+    /// Lowercase aliases, for finding what an alias resolves to
+    pub alias_lower: Field,
+    pub url: Field,
+    pub tags: Field,
+    pub synopsis: Field,
+    pub syntax: Field,
+    pub description: Field,
+    pub notes: Field,
+    pub param: Field,
+    pub param_type: Field,
+    pub param_help: Field,
+    /// Parameters as json
+    pub parameters: Field,
+    pub example_code: Field,
+    pub example_remarks: Field,
+    /// Examples as json
+    pub examples: Field,
+    /// Related links as json
+    pub links: Field,
+    /// Module information as json
+    pub module_info: Field,
+    pub facets: Field,
+}
+
+impl CmdletSchema {
+    /// Build the schema for a new index
+    pub fn new() -> CmdletSchema {
+        let mut schema_builder = SchemaBuilder::default();
+
+        let indexed_text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+                .set_tokenizer("en_stem"),
+        );
+        let stored_text_options = indexed_text_options.clone().set_stored();
+
+        let cmdlet_name_options = TextOptions::default().set_stored().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::Basic)
+                .set_tokenizer("pascal"),
+        );
+
+        let param_name_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+                .set_tokenizer("pascal"),
+        );
+
+        schema_builder.add_text_field("module_name", TEXT | STORED);
+        schema_builder.add_text_field("module_version", STORED);
+        schema_builder.add_text_field("module_id", STRING);
+        schema_builder.add_text_field("module_key", STRING);
+        schema_builder.add_text_field("name", cmdlet_name_options);
+        schema_builder.add_text_field("name_lower", STRING);
+        schema_builder.add_text_field("name_suffix", STRING);
+        schema_builder.add_text_field("aliases", STORED);
+        schema_builder.add_text_field("alias_lower", STRING);
+        schema_builder.add_text_field("url", STORED);
+        schema_builder.add_text_field("tags", TEXT | STORED);
+        schema_builder.add_text_field("synopsis", stored_text_options.clone());
+        schema_builder.add_text_field("syntax", TEXT | STORED);
+        schema_builder.add_text_field("description", stored_text_options.clone());
+        schema_builder.add_text_field("notes", stored_text_options);
+        schema_builder.add_text_field("param", param_name_options);
+        schema_builder.add_text_field("paramtype", TEXT);
+        schema_builder.add_text_field("paramhelp", indexed_text_options.clone());
+        schema_builder.add_text_field("parameters", STORED);
+        schema_builder.add_text_field("examplecode", TEXT);
+        schema_builder.add_text_field("exampleremarks", indexed_text_options);
+        schema_builder.add_text_field("examples", STORED);
+        schema_builder.add_text_field("links", STORED);
+        schema_builder.add_text_field("module_info", STORED);
+        schema_builder.add_facet_field("facets");
+
+        CmdletSchema::from_schema(schema_builder.build()).expect("all fields were just added")
+    }
+
+    /// Field handles for an existing index, failing if any are missing
+    pub fn for_index(index: &Index) -> Result<CmdletSchema, SchemaError> {
+        CmdletSchema::from_schema(index.schema())
+    }
+
+    fn from_schema(schema: Schema) -> Result<CmdletSchema, SchemaError> {
+        let field = |name: &'static str| {
+            schema
+                .get_field(name)
+                .ok_or(SchemaError::MissingField(name))
+        };
+
+        Ok(CmdletSchema {
+            module_name: field("module_name")?,
+            module_version: field("module_version")?,
+            module_id: field("module_id")?,
+            module_key: field("module_key")?,
+            name: field("name")?,
+            name_lower: field("name_lower")?,
+            name_suffix: field("name_suffix")?,
+            aliases: field("aliases")?,
+            alias_lower: field("alias_lower")?,
+            url: field("url")?,
+            tags: field("tags")?,
+            synopsis: field("synopsis")?,
+            syntax: field("syntax")?,
+            description: field("description")?,
+            notes: field("notes")?,
+            param: field("param")?,
+            param_type: field("paramtype")?,
+            param_help: field("paramhelp")?,
+            parameters: field("parameters")?,
+            example_code: field("examplecode")?,
+            example_remarks: field("exampleremarks")?,
+            examples: field("examples")?,
+            links: field("links")?,
+            module_info: field("module_info")?,
+            facets: field("facets")?,
+            schema,
+        })
+    }
+
+    pub fn schema(&self) -> Schema {
+        self.schema.clone()
+    }
+
+    /// Fields searched by queries which don't name a field
+    pub fn query_fields(&self) -> Vec<Field> {
+        vec![
+            self.module_name,
+            self.name,
+            self.tags,
+            self.synopsis,
+            self.syntax,
+            self.description,
+            self.notes,
+            self.param,
+            self.param_type,
+            self.param_help,
+            self.example_code,
+            self.example_remarks,
+        ]
+    }
+
+    /// Term matching cmdlets with the given facet value
+    pub fn facet_term(&self, facet_name: &str, value: &str) -> Term {
+        Term::from_facet(self.facets, &facet(facet_name, value))
+    }
+
+    pub fn to_document(&self, cmdlet: &Cmdlet) -> Result<Document, SchemaError> {
+        let mut document = doc!(
+            self.module_name => cmdlet.module.clone(),
+            self.module_version => cmdlet.module_version.clone(),
+            self.module_id => cmdlet.module.to_lowercase(),
+            self.module_key => module_key(&cmdlet.module, &cmdlet.module_version),
+            self.name => cmdlet.name.clone(),
+            self.url => cmdlet.url.clone(),
+            self.tags => cmdlet.tags.join(" "),
+            self.synopsis => cmdlet.synopsis.clone(),
+            self.syntax => cmdlet.syntax.clone(),
+            self.description => cmdlet.description.clone(),
+            self.notes => cmdlet.notes.clone(),
+        );
+
+        // Suffixes are shared between names, so each is followed by the name
+        // it came from
+        let name_lower = cmdlet.name.trim().to_lowercase();
+        for suffix in pascal_splitter::word_suffixes(cmdlet.name.trim()) {
+            document.add_text(
+                self.name_suffix,
+                &format!("{}{}{}", suffix, NAME_SUFFIX_SEPARATOR, name_lower),
+            );
+        }
+        document.add_text(self.name_lower, &name_lower);
+
+        for alias in cmdlet
+            .aliases
+            .iter()
+            .map(|a| a.trim())
+            .filter(|a| !a.is_empty())
+        {
+            document.add_text(self.aliases, alias);
+            document.add_text(self.alias_lower, &alias.to_lowercase());
+        }
+
+        for parameter in &cmdlet.parameters {
+            document.add_text(self.param, &parameter.name);
+            for alias in &parameter.aliases {
+                document.add_text(self.param, alias);
+            }
+            if !parameter.type_name.is_empty() {
+                document.add_text(self.param_type, &parameter.type_name);
+            }
+            if !parameter.description.is_empty() {
+                document.add_text(self.param_help, &parameter.description);
+            }
+        }
+        let parameters = serde_json::to_string(&cmdlet.parameters)
+            .map_err(|e| SchemaError::InvalidJson("parameters", e))?;
+        document.add_text(self.parameters, &parameters);
+
+        for example in &cmdlet.examples {
+            if !example.code.is_empty() {
+                document.add_text(self.example_code, &example.code);
+            }
+            if !example.remarks.is_empty() {
+                document.add_text(self.example_remarks, &example.remarks);
+            }
+        }
+        let examples = serde_json::to_string(&cmdlet.examples)
+            .map_err(|e| SchemaError::InvalidJson("examples", e))?;
+        document.add_text(self.examples, &examples);
+
+        let links = serde_json::to_string(&cmdlet.links)
+            .map_err(|e| SchemaError::InvalidJson("links", e))?;
+        document.add_text(self.links, &links);
+
+        let module_info = serde_json::to_string(&cmdlet.module_info)
+            .map_err(|e| SchemaError::InvalidJson("module_info", e))?;
+        document.add_text(self.module_info, &module_info);
+
+        document.add_facet(self.facets, facet("module", &cmdlet.module));
+        for tag in cmdlet
+            .tags
+            .iter()
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+        {
+            document.add_facet(self.facets, facet("tag", tag));
+        }
+        if let Some(verb) = cmdlet.verb() {
+            document.add_facet(self.facets, facet("verb", verb));
+        }
+        document.add_facet(self.facets, facet("source", cmdlet.source.as_str()));
+
+        Ok(document)
+    }
+
+    pub fn from_document(&self, doc: &Document) -> Result<Cmdlet, SchemaError> {
+        let text = |field: Field| doc.get_first(field).and_then(Value::text).unwrap_or("");
+        let source = doc
+            .get_all(self.facets)
+            .into_iter()
+            .filter_map(|value| match value {
+                Value::Facet(facet) => match facet.to_path().as_slice() {
+                    ["source", source] => Source::parse(source),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .next()
+            .ok_or(SchemaError::MissingValue("source"))?;
+
+        Ok(Cmdlet {
+            module: text(self.module_name).trim().to_string(),
+            module_version: text(self.module_version).trim().to_string(),
+            name: text(self.name).trim().to_string(),
+            aliases: doc
+                .get_all(self.aliases)
+                .into_iter()
+                .filter_map(Value::text)
+                .map(String::from)
+                .collect(),
+            url: text(self.url).to_string(),
+            tags: text(self.tags)
+                .split(' ')
+                .filter(|t| !t.trim().is_empty())
+                .map(|s| s.trim().to_string())
+                .collect(),
+            synopsis: text(self.synopsis).trim().to_string(),
+            syntax: text(self.syntax).trim().to_string(),
+            description: text(self.description).trim().to_string(),
+            notes: text(self.notes).trim().to_string(),
+            parameters: stored_json(doc, self.parameters, "parameters")?,
+            examples: stored_json(doc, self.examples, "examples")?,
+            links: stored_json(doc, self.links, "links")?,
+            module_info: stored_json(doc, self.module_info, "module_info")?,
+            source,
+        })
+    }
+}
+
+fn stored_json<T: serde::de::DeserializeOwned + Default>(
+    doc: &Document,
+    field: Field,
+    name: &'static str,
+) -> Result<T, SchemaError> {
+    match doc.get_first(field).and_then(Value::text) {
+        Some(json) => serde_json::from_str(json).map_err(|e| SchemaError::InvalidJson(name, e)),
+        None => Ok(Default::default()),
+    }
+}
+
+impl Default for CmdletSchema {
+    fn default() -> Self {
+        CmdletSchema::new()
+    }
+}
+
+#[test]
+fn document_round_trip() {
+    use crate::cmdlet::{Example, Link, ModuleInfo, Parameter};
+
+    let cmdlet = Cmdlet {
+        module: "ActiveDirectory".to_string(),
+        module_version: "1.0.1.0".to_string(),
+        name: "Get-ADUser".to_string(),
+        aliases: vec!["gadu".to_string()],
+        url: "https://example.com/get-aduser".to_string(),
+        tags: vec!["AD".to_string(), "Users".to_string()],
+        synopsis: "Gets one or more Active Directory users.".to_string(),
+        syntax: "Get-ADUser [-Identity] <ADUser>".to_string(),
+        description: "The Get-ADUser cmdlet gets a user object.".to_string(),
+        notes: "".to_string(),
+        parameters: vec![Parameter {
+            name: "Identity".to_string(),
+            type_name: "ADUser".to_string(),
+            mandatory: true,
+            pipeline_by_value: true,
+            ..Default::default()
+        }],
+        examples: vec![Example {
+            title: "Example 1: Get a user".to_string(),
+            code: "Get-ADUser -Identity ChewDavid".to_string(),
+            remarks: "Gets the user ChewDavid.".to_string(),
+        }],
+        links: vec![Link {
+            title: "Online Version:".to_string(),
+            uri: "https://example.com/get-aduser".to_string(),
+        }],
+        module_info: ModuleInfo {
+            author: "Microsoft Corporation".to_string(),
+            compatible_editions: vec!["Desktop".to_string()],
+            ..Default::default()
+        },
+        source: Source::Rsat,
+    };
+
+    let schema = CmdletSchema::new();
+    let document = schema.to_document(&cmdlet).unwrap();
+    assert_eq!(schema.from_document(&document).unwrap(), cmdlet);
+    assert_eq!(
+        suffix_term_name("aduser\u{0}get-aduser"),
+        Some("get-aduser")
+    );
+}
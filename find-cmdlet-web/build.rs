@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Context};
 use ructe::Ructe;
 use std::env;
-use std::{path::Path, process::Command};
+use std::{fs::File, io, path::Path, process::Command};
 
 fn compile_typescript(out_dir: &str) -> anyhow::Result<()> {
     println!("cargo:rerun-if-changed=typescript/tsconfig.json");
@@ -56,7 +56,7 @@ fn compile_sass(statics_dir: &str) -> anyhow::Result<()> {
     println!("cargo:rerun-if-changed=sass/style.scss");
 
     let status = Command::new("sass")
-        .arg("-s")
+        .arg("--style")
         .arg("compressed")
         .arg("sass/style.scss")
         .arg(Path::new(statics_dir).join("style.css"))
@@ -70,6 +70,51 @@ fn compile_sass(statics_dir: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Write brotli and gzip variants of a file alongside it, eg. style.css.br, so
+/// they can be served without compressing on every request
+fn precompress(file_path: &Path) -> anyhow::Result<()> {
+    let variant = |extension: &str| {
+        let mut variant_path = file_path.as_os_str().to_owned();
+        variant_path.push(".");
+        variant_path.push(extension);
+        File::create(&variant_path)
+            .with_context(|| format!("Failed to create {}", Path::new(&variant_path).display()))
+    };
+    let read =
+        || File::open(file_path).with_context(|| format!("Failed to open {}", file_path.display()));
+
+    let mut brotli = brotli2::write::BrotliEncoder::new(variant("br")?, 11);
+    io::copy(&mut read()?, &mut brotli).context("Failed to brotli compress")?;
+    brotli
+        .finish()
+        .context("Failed to finish brotli compression")?;
+
+    let mut gzip = flate2::write::GzEncoder::new(variant("gz")?, flate2::Compression::best());
+    io::copy(&mut read()?, &mut gzip).context("Failed to gzip compress")?;
+    gzip.finish().context("Failed to finish gzip compression")?;
+
+    Ok(())
+}
+
+/// Precompress the static CSS, JavaScript and SVG files
+fn precompress_statics(dirs: &[&str]) -> anyhow::Result<()> {
+    for dir in dirs {
+        for entry in Path::new(dir)
+            .read_dir()
+            .with_context(|| format!("Failed to read {}", dir))?
+        {
+            let file_path = entry.context("Failed to read directory entry")?.path();
+            let extension = file_path.extension().and_then(|e| e.to_str());
+            if let Some("css") | Some("js") | Some("svg") = extension {
+                println!("cargo:rerun-if-changed={}", file_path.to_string_lossy());
+                precompress(&file_path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn main() -> anyhow::Result<()> {
     let out_dir = env::var("OUT_DIR")?;
     let static_dir = "static";
@@ -77,6 +122,7 @@ fn main() -> anyhow::Result<()> {
     compile_typescript(&out_dir)?;
     uglify_javascript(&out_dir, static_dir)?;
     compile_sass(static_dir)?;
+    precompress_statics(&[static_dir, "assets"])?;
 
     let mut ructe = Ructe::from_env()?;
 
@@ -1,14 +1,26 @@
 use actix::prelude::*;
 use actix_files as fs;
-use actix_web::{http, middleware, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use actix_web::{
+    dev::BodyEncoding, http, middleware, web, App, HttpRequest, HttpResponse, HttpServer, Responder,
+};
 use anyhow::Context;
-use find_cmdlet_index::pascal_splitter;
+use find_cmdlet_index::{
+    cmdlet::{Cmdlet, Example, Link, ModuleInfo, Parameter},
+    metadata::IndexMetadata,
+    pascal_splitter,
+    schema::{self, CmdletSchema, FACETS},
+};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::File,
     io::{BufReader, Read},
     path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
 };
 
 include!(concat!(env!("OUT_DIR"), "/templates.rs"));
@@ -19,6 +31,81 @@ struct SearchQuery {
     query: String,
     #[serde(rename = "t")]
     ty: Option<String>,
+    /// Page of results to show, starting from 1
+    page: Option<usize>,
+    /// Number of results to skip, used instead of page when given
+    offset: Option<usize>,
+    /// Number of results per page
+    limit: Option<usize>,
+    module: Option<String>,
+    tag: Option<String>,
+    verb: Option<String>,
+    source: Option<String>,
+}
+
+impl SearchQuery {
+    /// Facet filters given in the query, as (facet, value) pairs
+    fn filters(&self) -> Vec<(&'static str, &str)> {
+        FACETS
+            .iter()
+            .zip(&[&self.module, &self.tag, &self.verb, &self.source])
+            .filter_map(|(facet, value)| match value.as_deref() {
+                Some(value) if !value.trim().is_empty() => Some((*facet, value.trim())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct SuggestQuery {
+    #[serde(rename = "q")]
+    query: String,
+}
+
+/// Number of completions returned for a partial cmdlet name
+const SUGGESTIONS: usize = 10;
+
+/// Most terms to consider when completing a partial cmdlet name, so very short
+/// prefixes don't walk the whole term dictionary. Terms are read in
+/// alphabetical order, so for prefixes matching more than this only the first
+/// terms alphabetically are ranked by popularity.
+const MAX_SUGGESTION_TERMS: usize = 10_000;
+
+/// Number of results per page when no limit is given
+const DEFAULT_LIMIT: usize = 30;
+
+/// Number of values to count for each facet
+const FACET_VALUES: usize = 10;
+
+/// Boost for a cmdlet whose alias is the whole query, so searching for an
+/// alias finds what it resolves to first
+const ALIAS_BOOST: f32 = 10.0;
+
+const SEARCH_PATH: &str = "/search";
+const API_SEARCH_PATH: &str = "/api/v1/search";
+
+/// OpenAPI description of the JSON API
+const OPENAPI: &str = include_str!("../openapi.json");
+
+fn search_url(
+    path: &str,
+    query: &SearchQuery,
+    filters: &[(&str, &str)],
+    offset: usize,
+    limit: usize,
+) -> String {
+    let mut url = format!("{}?q={}", path, encode_path_segment(&query.query));
+    for (facet, value) in filters {
+        url.push_str(&format!("&{}={}", facet, encode_path_segment(value)));
+    }
+    url.push_str(&format!("&offset={}&limit={}", offset, limit));
+    if let Some(ty) = &query.ty {
+        url.push_str("&t=");
+        url.push_str(&encode_path_segment(ty));
+    }
+
+    url
 }
 
 #[derive(Serialize)]
@@ -26,50 +113,434 @@ pub struct CmdletResult {
     module_name: String,
     module_version: String,
     name: String,
+    aliases: Vec<String>,
+    /// The cmdlet's alias the query was for, eg. gci for Get-ChildItem
+    matched_alias: Option<String>,
     url: String,
     tags: Vec<String>,
     synopsis: String,
     syntax: String,
+    examples: Vec<Example>,
+    module_info: ModuleInfo,
     score: f32,
 }
 
+impl CmdletResult {
+    fn new(cmdlet: Cmdlet, score: f32, alias: Option<&str>) -> CmdletResult {
+        let matched_alias = alias.and_then(|alias| {
+            cmdlet
+                .aliases
+                .iter()
+                .find(|a| a.eq_ignore_ascii_case(alias))
+                .cloned()
+        });
+
+        CmdletResult {
+            module_name: cmdlet.module,
+            module_version: cmdlet.module_version,
+            name: cmdlet.name,
+            aliases: cmdlet.aliases,
+            matched_alias,
+            url: cmdlet.url,
+            tags: cmdlet.tags,
+            synopsis: cmdlet.synopsis,
+            syntax: cmdlet.syntax,
+            examples: cmdlet.examples,
+            module_info: cmdlet.module_info,
+            score,
+        }
+    }
+
+    /// Link to the cmdlet's page on this site
+    pub fn detail_url(&self) -> String {
+        cmdlet_url(&self.module_name, &self.module_version, &self.name)
+    }
+
+    /// Link to the cmdlet's module page on this site
+    pub fn module_url(&self) -> String {
+        module_url(&self.module_name, &self.module_version)
+    }
+}
+
+/// A page of search results
+pub struct SearchResults {
+    /// Total number of cmdlets matching the query
+    total: usize,
+    cmdlets: Vec<CmdletResult>,
+    /// Top values for each facet, in the same order as FACETS
+    facets: Vec<(&'static str, Vec<(String, u64)>)>,
+}
+
+/// Position of a page within the search results, with links to neighbouring
+/// pages
+#[derive(Default, Serialize)]
+pub struct Pagination {
+    total: usize,
+    offset: usize,
+    limit: usize,
+    prev: Option<String>,
+    next: Option<String>,
+}
+
+impl Pagination {
+    fn new(
+        path: &str,
+        query: &SearchQuery,
+        total: usize,
+        offset: usize,
+        limit: usize,
+    ) -> Pagination {
+        let filters = query.filters();
+        let url = |offset: usize| search_url(path, query, &filters, offset, limit);
+
+        Pagination {
+            total,
+            offset,
+            limit,
+            prev: if offset > 0 {
+                Some(url(offset.saturating_sub(limit)))
+            } else {
+                None
+            },
+            next: if offset + limit < total {
+                Some(url(offset + limit))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Position of the first result on the page, counting from 1
+    pub fn first(&self) -> usize {
+        std::cmp::min(self.offset + 1, self.total)
+    }
+
+    /// Position of the last result on the page, counting from 1
+    pub fn last(&self) -> usize {
+        std::cmp::min(self.offset + self.limit, self.total)
+    }
+}
+
+/// A value of a facet, with the number of matching results and a link to
+/// toggle filtering by it
+#[derive(Serialize)]
+pub struct FacetValue {
+    value: String,
+    count: u64,
+    selected: bool,
+    url: String,
+}
+
+pub struct FacetGroup {
+    name: &'static str,
+    values: Vec<FacetValue>,
+}
+
+impl FacetGroup {
+    pub fn title(&self) -> &'static str {
+        match self.name {
+            "module" => "Module",
+            "tag" => "Tag",
+            "verb" => "Verb",
+            "source" => "Source",
+            _ => self.name,
+        }
+    }
+}
+
+/// Facet counts for a search, serialized as an object keyed by facet name
+#[derive(Default)]
+pub struct Facets(Vec<FacetGroup>);
+
+impl Facets {
+    fn new(
+        path: &str,
+        query: &SearchQuery,
+        counts: Vec<(&'static str, Vec<(String, u64)>)>,
+        limit: usize,
+    ) -> Facets {
+        let filters = query.filters();
+
+        Facets(
+            counts
+                .into_iter()
+                .map(|(name, values)| FacetGroup {
+                    name,
+                    values: values
+                        .into_iter()
+                        .map(|(value, count)| {
+                            let selected = filters
+                                .iter()
+                                .any(|(f, v)| *f == name && v.eq_ignore_ascii_case(&value));
+                            let mut toggled = filters
+                                .iter()
+                                .filter(|(f, _)| *f != name)
+                                .cloned()
+                                .collect::<Vec<_>>();
+                            if !selected {
+                                toggled.push((name, &value));
+                            }
+                            let url = search_url(path, query, &toggled, 0, limit);
+
+                            FacetValue {
+                                value,
+                                count,
+                                selected,
+                                url,
+                            }
+                        })
+                        .collect(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Facets which have any values
+    pub fn groups(&self) -> impl Iterator<Item = &FacetGroup> {
+        self.0.iter().filter(|g| !g.values.is_empty())
+    }
+}
+
+impl Serialize for Facets {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|g| (g.name, &g.values)))
+    }
+}
+
+#[derive(Serialize)]
+struct SearchResponse<'a> {
+    #[serde(flatten)]
+    pagination: &'a Pagination,
+    results: &'a [CmdletResult],
+    facets: &'a Facets,
+}
+
+/// Response from /api/v1/search. Fields may be added, but won't be removed or
+/// change meaning within v1.
+#[derive(Serialize)]
+struct ApiSearchResponse<'a> {
+    query: &'a str,
+    filters: HashMap<&'static str, &'a str>,
+    /// Page of results, counting from 1
+    page: usize,
+    #[serde(flatten)]
+    pagination: &'a Pagination,
+    /// Time taken to search, in milliseconds
+    took_ms: f64,
+    results: &'a [CmdletResult],
+    facets: &'a Facets,
+}
+
+#[derive(Serialize)]
+struct ApiError<'a> {
+    /// Machine readable kind of error: bad_request, invalid_query, overloaded
+    /// or internal
+    kind: &'static str,
+    error: &'a str,
+}
+
+/// Everything stored in the index for a single cmdlet
+pub struct CmdletDetails {
+    module_name: String,
+    module_version: String,
+    name: String,
+    aliases: Vec<String>,
+    url: String,
+    tags: Vec<String>,
+    synopsis: String,
+    syntax: String,
+    description: String,
+    notes: String,
+    parameters: Vec<Parameter>,
+    examples: Vec<Example>,
+    links: Vec<Link>,
+}
+
+impl From<Cmdlet> for CmdletDetails {
+    fn from(cmdlet: Cmdlet) -> CmdletDetails {
+        CmdletDetails {
+            module_name: cmdlet.module,
+            module_version: cmdlet.module_version,
+            name: cmdlet.name,
+            aliases: cmdlet.aliases,
+            url: cmdlet.url,
+            tags: cmdlet.tags,
+            synopsis: cmdlet.synopsis,
+            syntax: cmdlet.syntax,
+            description: cmdlet.description,
+            notes: cmdlet.notes,
+            parameters: cmdlet.parameters,
+            examples: cmdlet.examples,
+            links: cmdlet.links,
+        }
+    }
+}
+
+impl CmdletDetails {
+    /// Link to the cmdlet's module page on this site
+    pub fn module_url(&self) -> String {
+        module_url(&self.module_name, &self.module_version)
+    }
+}
+
+/// A command listed on a module page
+pub struct ModuleCommand {
+    name: String,
+    synopsis: String,
+    detail_url: String,
+}
+
+/// Everything needed to display a version of a module
+pub struct ModuleDetails {
+    name: String,
+    version: String,
+    versions: Vec<String>,
+    tags: Vec<String>,
+    info: ModuleInfo,
+    commands: Vec<ModuleCommand>,
+}
+
+impl ModuleDetails {
+    /// Link to another version of the module
+    pub fn version_url(&self, version: &str) -> String {
+        module_url(&self.name, version)
+    }
+}
+
+/// Percent-encode a single segment of a URL path
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Whether a URL from help or package metadata is safe to link to. Anything
+/// else, eg. javascript: URLs, is shown as text.
+fn is_web_url(url: &str) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+fn cmdlet_url(module_name: &str, module_version: &str, name: &str) -> String {
+    format!(
+        "/cmdlet/{}/{}/{}",
+        encode_path_segment(module_name),
+        encode_path_segment(module_version),
+        encode_path_segment(name)
+    )
+}
+
+fn module_url(module_name: &str, module_version: &str) -> String {
+    format!(
+        "/module/{}/{}",
+        encode_path_segment(module_name),
+        encode_path_segment(module_version)
+    )
+}
+
+/// Compare module versions, numerically where possible, eg. 1.10.0 > 1.9.2
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |v: &str| {
+        v.split(&['.', '-'][..])
+            .map(|p| p.to_ascii_lowercase())
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (parts(a), parts(b));
+    for (a, b) in a.iter().zip(b.iter()) {
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        };
+        if ordering != std::cmp::Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    a.len().cmp(&b.len())
+}
+
 #[derive(Debug)]
 enum SearchError {
     Tantivy(tantivy::TantivyError),
-    TantivyQuery(tantivy::query::QueryParserError),
-    None,
+    /// The query couldn't be parsed, which is the user's mistake rather than
+    /// a problem with the index
+    InvalidQuery(tantivy::query::QueryParserError),
+}
+
+impl SearchError {
+    /// Explanation of an invalid query, for showing to whoever wrote it
+    fn invalid_query_message(error: &tantivy::query::QueryParserError) -> String {
+        use tantivy::query::QueryParserError::*;
+
+        match error {
+            SyntaxError => {
+                "the query has a syntax error, check quotes and brackets are balanced".to_string()
+            }
+            FieldDoesNotExist(field) => format!("there is no field called \"{}\"", field),
+            FieldNotIndexed(field) => format!("the field \"{}\" can't be searched", field),
+            FieldDoesNotHavePositionsIndexed(field) => format!(
+                "the field \"{}\" can only be searched for single words, not phrases",
+                field
+            ),
+            AllButQueryForbidden => {
+                "the query only excludes words, add something to search for".to_string()
+            }
+            RangeMustNotHavePhrase => "range bounds must be single words".to_string(),
+            ExpectedInt(e) => format!("expected a whole number: {}", e),
+            ExpectedFloat(e) => format!("expected a number: {}", e),
+            DateFormatError(e) => format!("expected an RFC 3339 date: {}", e),
+            e => e.to_string(),
+        }
+    }
 }
 
 impl std::fmt::Display for SearchError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
             SearchError::Tantivy(te) => te.fmt(f),
-            SearchError::TantivyQuery(te) => te.fmt(f),
-            SearchError::None => f.write_str("none error"),
+            SearchError::InvalidQuery(te) => f.write_str(&SearchError::invalid_query_message(te)),
         }
     }
 }
 
 impl std::error::Error for SearchError {}
 
-fn search_index(index: &tantivy::Index, query_str: &str) -> anyhow::Result<Vec<CmdletResult>> {
-    let reader = index
-        .reader()
-        .map_err(SearchError::Tantivy)
-        .context("could not get reader for index")?;
-    let searcher = reader.searcher();
-    let query_parser = tantivy::query::QueryParser::for_index(
-        &index,
-        index
-            .schema()
-            .fields()
-            .filter(|f| f.1.is_indexed())
-            .map(|f| f.0)
-            .collect(),
-    );
+fn search_index(
+    served: &ServedIndex,
+    query_str: &str,
+    filters: &[(&str, &str)],
+    offset: usize,
+    limit: usize,
+) -> anyhow::Result<SearchResults> {
+    let schema = &served.schema;
+    let searcher = served.reader.searcher();
+    let query_parser = tantivy::query::QueryParser::for_index(&served.index, schema.query_fields());
+
+    // Searching for an alias also finds the cmdlet it resolves to, boosted to
+    // the top. Aliases like % and ? aren't valid queries, so are looked up
+    // before parsing.
+    let alias = query_str.trim();
+    let alias_term = tantivy::Term::from_field_text(schema.alias_lower, &alias.to_lowercase());
+    let alias = if searcher.doc_freq(&alias_term) > 0 {
+        Some(alias)
+    } else {
+        None
+    };
+
     let query = query_parser
         .parse_query(query_str)
-        .or_else(|_| {
+        .or_else(|error| {
+            // Searching a field which doesn't exist is worth pointing out,
+            // rather than quietly searching for the mangled term
+            if let tantivy::query::QueryParserError::FieldDoesNotExist(_) = error {
+                return Err(error);
+            }
             let query_str: String = query_str
                 .chars()
                 .filter_map(|c| {
@@ -85,121 +556,325 @@ fn search_index(index: &tantivy::Index, query_str: &str) -> anyhow::Result<Vec<C
                 })
                 .collect();
 
-            query_parser.parse_query(&query_str)
+            if query_str.is_empty() {
+                return Err(error);
+            }
+
+            // Report what was wrong with the query as written, rather than
+            // with the simplified version
+            query_parser.parse_query(&query_str).map_err(|_| error)
         })
-        .map_err(SearchError::TantivyQuery)
-        .with_context(|| format!("could not parse query string: {}", query_str))?;
+        .map_err(SearchError::InvalidQuery);
 
-    let docs = searcher
-        .search(&query, &tantivy::collector::TopDocs::with_limit(30))
+    let query = match alias {
+        Some(_) => {
+            let alias_query = Box::new(tantivy::query::BoostQuery::new(
+                Box::new(tantivy::query::TermQuery::new(
+                    alias_term,
+                    tantivy::schema::IndexRecordOption::Basic,
+                )),
+                ALIAS_BOOST,
+            )) as Box<dyn tantivy::query::Query>;
+            match query {
+                Ok(query) => Box::new(tantivy::query::BooleanQuery::from(vec![
+                    (tantivy::query::Occur::Should, query),
+                    (tantivy::query::Occur::Should, alias_query),
+                ])),
+                Err(_) => alias_query,
+            }
+        }
+        None => query.with_context(|| format!("could not parse query string: {}", query_str))?,
+    };
+
+    let query = if filters.is_empty() {
+        query
+    } else {
+        let mut clauses = vec![(tantivy::query::Occur::Must, query)];
+        for (facet, value) in filters {
+            clauses.push((
+                tantivy::query::Occur::Must,
+                Box::new(tantivy::query::TermQuery::new(
+                    schema.facet_term(facet, value),
+                    tantivy::schema::IndexRecordOption::Basic,
+                )) as Box<dyn tantivy::query::Query>,
+            ));
+        }
+        Box::new(tantivy::query::BooleanQuery::from(clauses))
+    };
+
+    // TopDocs has no offset in this version of tantivy, so collect everything
+    // up to the end of the page and skip the start
+    let top_docs = tantivy::collector::TopDocs::with_limit(offset + limit);
+    let mut facet_collector = tantivy::collector::FacetCollector::for_field(schema.facets);
+    for facet in FACETS {
+        facet_collector.add_facet(tantivy::schema::Facet::from_path(vec![*facet]));
+    }
+    let (docs, total, facet_counts) = searcher
+        .search(
+            &query,
+            &(top_docs, tantivy::collector::Count, facet_collector),
+        )
         .map_err(SearchError::Tantivy)
         .with_context(|| format!("searching failed for query: {}", query_str))?;
 
-    // TODO should probably share this with indexer?
-    let module_name = index
-        .schema()
-        .get_field("module_name")
-        .context("could not find module name in index")?;
-    let module_version = index
-        .schema()
-        .get_field("module_version")
-        .context("could not find module version in index")?;
-    let name = index
-        .schema()
-        .get_field("name")
-        .context("could not find name in index")?;
-    let url = index
-        .schema()
-        .get_field("url")
-        .context("could not find url in index")?;
-    let tags = index
-        .schema()
-        .get_field("tags")
-        .context("could not find tags in index")?;
-    let synopsis = index
-        .schema()
-        .get_field("synopsis")
-        .context("could not find synopsis in index")?;
-    let syntax = index
-        .schema()
-        .get_field("syntax")
-        .context("could not find syntax in index")?;
-
-    Ok(docs
+    let facets = FACETS
+        .iter()
+        .map(|facet| {
+            let values = facet_counts
+                .top_k(
+                    tantivy::schema::Facet::from_path(vec![*facet]),
+                    FACET_VALUES,
+                )
+                .into_iter()
+                .filter_map(|(value, count)| Some((value.to_path().last()?.to_string(), count)))
+                .collect();
+            (*facet, values)
+        })
+        .collect();
+
+    let cmdlets = docs
         .into_iter()
+        .skip(offset)
         .map(|(score, doc_addr)| -> anyhow::Result<_> {
             let doc = searcher
                 .doc(doc_addr)
                 .map_err(SearchError::Tantivy)
                 .with_context(|| format!("could not find document for query: {}", query_str))?;
+            let cmdlet = schema
+                .from_document(&doc)
+                .with_context(|| format!("could not load document for query: {}", query_str))?;
 
-            let module_name = doc
-                .get_first(module_name)
-                .ok_or(SearchError::None)
-                .context("could not find module name")?
-                .text()
-                .ok_or(SearchError::None)
-                .context("could not find module name text")?;
-            let module_version = doc
-                .get_first(module_version)
-                .ok_or(SearchError::None)
-                .context("could not find module version")?
-                .text()
-                .ok_or(SearchError::None)
-                .context("could not find module version text")?;
-            let name = doc
-                .get_first(name)
-                .ok_or(SearchError::None)
-                .context("could not find cmdlet name")?
-                .text()
-                .ok_or(SearchError::None)
-                .context("could not find cmdlet name text")?;
-            let url = doc
-                .get_first(url)
-                .ok_or(SearchError::None)
-                .context("could not find url")?
-                .text()
-                .ok_or(SearchError::None)
-                .context("could not find url text")?;
-            let tags = doc
-                .get_first(tags)
-                .ok_or(SearchError::None)
-                .context("could not find tags")?
-                .text()
-                .ok_or(SearchError::None)
-                .context("could not find tags text")?;
-            let synopsis = doc
-                .get_first(synopsis)
-                .ok_or(SearchError::None)
-                .context("could not find synopsis")?
-                .text()
-                .ok_or(SearchError::None)
-                .context("could not find synopsis text")?;
-            let syntax = doc
-                .get_first(syntax)
-                .ok_or(SearchError::None)
-                .context("could not find syntax")?
-                .text()
-                .ok_or(SearchError::None)
-                .context("could not find syntax text")?;
-
-            Ok(CmdletResult {
-                module_name: module_name.trim().to_string(),
-                module_version: module_version.trim().to_string(),
-                name: name.trim().to_string(),
-                url: url.to_string(),
-                tags: tags
-                    .split(' ')
-                    .filter(|t| !t.trim().is_empty())
-                    .map(|s| s.trim().to_string())
-                    .collect(),
-                synopsis: synopsis.trim().to_string(),
-                syntax: syntax.trim().to_string(),
-                score,
-            })
+            Ok(CmdletResult::new(cmdlet, score, alias))
         })
         .flatten()
-        .collect())
+        .collect();
+
+    Ok(SearchResults {
+        total,
+        cmdlets,
+        facets,
+    })
+}
+
+/// Terms in a field starting with the prefix, most frequent first, out of the
+/// first MAX_SUGGESTION_TERMS alphabetically
+fn prefix_terms(
+    searcher: &tantivy::Searcher,
+    field: tantivy::schema::Field,
+    prefix: &str,
+) -> Vec<String> {
+    // Every term starting with the prefix sorts before this
+    let upper = format!("{}{}", prefix, std::char::MAX);
+
+    let mut doc_freqs = HashMap::<String, u32>::new();
+    for segment_reader in searcher.segment_readers() {
+        let inverted_index = segment_reader.inverted_index(field);
+        let mut stream = inverted_index
+            .terms()
+            .range()
+            .ge(prefix)
+            .lt(&upper)
+            .into_stream();
+        while doc_freqs.len() < MAX_SUGGESTION_TERMS && stream.advance() {
+            if let Ok(term) = std::str::from_utf8(stream.key()) {
+                *doc_freqs.entry(term.to_string()).or_insert(0) += stream.value().doc_freq;
+            }
+        }
+    }
+
+    let mut terms = doc_freqs.into_iter().collect::<Vec<_>>();
+    terms.sort_by(|(a, a_freq), (b, b_freq)| {
+        b_freq
+            .cmp(a_freq)
+            .then_with(|| a.len().cmp(&b.len()))
+            .then_with(|| a.cmp(b))
+    });
+
+    terms.into_iter().map(|(term, _)| term).collect()
+}
+
+/// Complete a partial cmdlet name, matching from the start of the name first,
+/// then from the start of any word in it, eg. Get-ADU and ADU both complete to
+/// Get-ADUser
+fn suggest_index(served: &ServedIndex, query_str: &str) -> anyhow::Result<Vec<String>> {
+    let prefix = query_str.trim().to_lowercase();
+    if prefix.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let schema = &served.schema;
+    let searcher = served.reader.searcher();
+
+    // Suffix terms are followed by the name they came from
+    let names = prefix_terms(&searcher, schema.name_lower, &prefix)
+        .into_iter()
+        .chain(
+            prefix_terms(&searcher, schema.name_suffix, &prefix)
+                .into_iter()
+                .filter_map(|term| schema::suffix_term_name(&term).map(str::to_string)),
+        );
+
+    let mut suggestions: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+    for term in names {
+        if suggestions.len() >= SUGGESTIONS {
+            break;
+        }
+        if !seen.insert(term.clone()) {
+            continue;
+        }
+
+        // Terms are lowercase, so find a cmdlet with the name to show it as
+        // it's written
+        let query = tantivy::query::TermQuery::new(
+            tantivy::Term::from_field_text(schema.name_lower, &term),
+            tantivy::schema::IndexRecordOption::Basic,
+        );
+        let docs = searcher
+            .search(&query, &tantivy::collector::TopDocs::with_limit(1))
+            .map_err(SearchError::Tantivy)
+            .with_context(|| format!("searching failed for suggestion: {}", term))?;
+        // Documents may have been deleted since the term was indexed
+        if let Some((_, doc_addr)) = docs.into_iter().next() {
+            let doc = searcher
+                .doc(doc_addr)
+                .map_err(SearchError::Tantivy)
+                .with_context(|| format!("could not find document for suggestion: {}", term))?;
+            let cmdlet = schema
+                .from_document(&doc)
+                .with_context(|| format!("could not load document for suggestion: {}", term))?;
+            if !cmdlet.name.is_empty() {
+                suggestions.push(cmdlet.name);
+            }
+        }
+    }
+
+    Ok(suggestions)
+}
+
+fn find_cmdlet(
+    served: &ServedIndex,
+    module_name: &str,
+    module_version: &str,
+    name: &str,
+) -> anyhow::Result<Option<CmdletDetails>> {
+    let schema = &served.schema;
+    let searcher = served.reader.searcher();
+
+    let module_key = schema::module_key(module_name, module_version);
+    let query = tantivy::query::TermQuery::new(
+        tantivy::Term::from_field_text(schema.module_key, &module_key),
+        tantivy::schema::IndexRecordOption::Basic,
+    );
+    let count = searcher
+        .search(&query, &tantivy::collector::Count)
+        .map_err(SearchError::Tantivy)
+        .with_context(|| format!("counting failed for module: {}", module_key))?;
+    if count == 0 {
+        return Ok(None);
+    }
+    let docs = searcher
+        .search(&query, &tantivy::collector::TopDocs::with_limit(count))
+        .map_err(SearchError::Tantivy)
+        .with_context(|| format!("searching failed for module: {}", module_key))?;
+
+    for (_, doc_addr) in docs {
+        let doc = searcher
+            .doc(doc_addr)
+            .map_err(SearchError::Tantivy)
+            .with_context(|| format!("could not find document for module: {}", module_key))?;
+        let cmdlet = schema
+            .from_document(&doc)
+            .with_context(|| format!("could not load document for module: {}", module_key))?;
+        if cmdlet.name.eq_ignore_ascii_case(name) {
+            return Ok(Some(cmdlet.into()));
+        }
+    }
+
+    Ok(None)
+}
+
+fn find_module(
+    served: &ServedIndex,
+    module_name: &str,
+    module_version: Option<&str>,
+) -> anyhow::Result<Option<ModuleDetails>> {
+    let schema = &served.schema;
+    let searcher = served.reader.searcher();
+
+    let query = tantivy::query::TermQuery::new(
+        tantivy::Term::from_field_text(schema.module_id, &module_name.to_lowercase()),
+        tantivy::schema::IndexRecordOption::Basic,
+    );
+    let count = searcher
+        .search(&query, &tantivy::collector::Count)
+        .map_err(SearchError::Tantivy)
+        .with_context(|| format!("counting failed for module: {}", module_name))?;
+    if count == 0 {
+        return Ok(None);
+    }
+    let cmdlets = searcher
+        .search(&query, &tantivy::collector::TopDocs::with_limit(count))
+        .map_err(SearchError::Tantivy)
+        .with_context(|| format!("searching failed for module: {}", module_name))?
+        .into_iter()
+        .map(|(_, doc_addr)| {
+            let doc = searcher
+                .doc(doc_addr)
+                .map_err(SearchError::Tantivy)
+                .with_context(|| format!("could not find document for module: {}", module_name))?;
+            schema
+                .from_document(&doc)
+                .with_context(|| format!("could not load document for module: {}", module_name))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut versions = cmdlets
+        .iter()
+        .map(|cmdlet| cmdlet.module_version.clone())
+        .collect::<Vec<_>>();
+    versions.sort_by(|a, b| compare_versions(b, a));
+    versions.dedup();
+
+    let version = match module_version {
+        Some(module_version) => match versions
+            .iter()
+            .find(|v| v.eq_ignore_ascii_case(module_version))
+        {
+            Some(version) => version.clone(),
+            None => return Ok(None),
+        },
+        None => versions[0].clone(),
+    };
+    let mut cmdlets = cmdlets
+        .into_iter()
+        .filter(|cmdlet| cmdlet.module_version == version)
+        .collect::<Vec<_>>();
+    let first = cmdlets.remove(0);
+
+    let module_name = first.module.clone();
+    let mut commands = std::iter::once(&first)
+        .chain(cmdlets.iter())
+        .map(|cmdlet| ModuleCommand {
+            detail_url: cmdlet_url(&module_name, &version, &cmdlet.name),
+            synopsis: cmdlet.synopsis.clone(),
+            name: cmdlet.name.clone(),
+        })
+        .collect::<Vec<_>>();
+    commands.sort_by(|a, b| {
+        a.name
+            .to_ascii_lowercase()
+            .cmp(&b.name.to_ascii_lowercase())
+    });
+
+    Ok(Some(ModuleDetails {
+        name: module_name,
+        version,
+        versions,
+        tags: first.tags,
+        info: first.module_info,
+        commands,
+    }))
 }
 
 fn ise(error: anyhow::Error) -> actix_web::error::Error {
@@ -208,34 +883,164 @@ fn ise(error: anyhow::Error) -> actix_web::error::Error {
     HttpResponse::InternalServerError().finish().into()
 }
 
+/// Reasons a search request can fail, which each endpoint reports in its own
+/// format
+enum SearchRequestError {
+    BadRequest(String),
+    InvalidQuery(String),
+    Overloaded,
+    Failed(anyhow::Error),
+}
+
+/// A page of search results
+struct SearchPage {
+    results: SearchResults,
+    offset: usize,
+    limit: usize,
+}
+
+async fn run_search(state: &State, query: &SearchQuery) -> Result<SearchPage, SearchRequestError> {
+    let max_results = state.config.max_results;
+    let limit = query
+        .limit
+        .unwrap_or_else(|| std::cmp::min(DEFAULT_LIMIT, max_results));
+    if limit == 0 || limit > max_results {
+        return Err(SearchRequestError::BadRequest(format!(
+            "limit must be between 1 and {}",
+            max_results
+        )));
+    }
+    let offset = match (query.offset, query.page) {
+        (Some(offset), _) => offset,
+        (None, Some(0)) => {
+            return Err(SearchRequestError::BadRequest(
+                "page must be at least 1".to_string(),
+            ));
+        }
+        (None, Some(page)) => (page - 1).saturating_mul(limit),
+        (None, None) => 0,
+    };
+    if offset > state.config.max_offset {
+        return Err(SearchRequestError::BadRequest(format!(
+            "offset must be at most {}",
+            state.config.max_offset
+        )));
+    }
+
+    // Searches run on their own threads, so slow queries can't tie up every
+    // HTTP worker. Refuse new searches once too many are waiting for one.
+    let _queued = match QueuedSearch::new(&state.queued_searches, state.config.max_queued_searches)
+    {
+        Some(queued) => queued,
+        None => {
+            log::warn!("search queue full, refusing search: {}", query.query);
+            return Err(SearchRequestError::Overloaded);
+        }
+    };
+    let results = state
+        .search
+        .send(SearchTantivyIndex {
+            query: query.query.clone(),
+            filters: query
+                .filters()
+                .into_iter()
+                .map(|(facet, value)| (facet, value.to_string()))
+                .collect(),
+            offset,
+            limit,
+        })
+        .await
+        .context("could not retrieve search results")
+        .map_err(SearchRequestError::Failed)?
+        .map_err(|e| {
+            if let Some(SearchError::InvalidQuery(error)) = e.downcast_ref::<SearchError>() {
+                return SearchRequestError::InvalidQuery(SearchError::invalid_query_message(error));
+            }
+            SearchRequestError::Failed(e.context("could not search index"))
+        })?;
+
+    Ok(SearchPage {
+        results,
+        offset,
+        limit,
+    })
+}
+
 async fn search(
     state: web::Data<State>,
     request: HttpRequest,
 ) -> actix_web::Result<impl Responder> {
     let query = web::Query::<SearchQuery>::from_query(request.query_string())?;
 
-    let results = search_index(&state.index, &query.query)
-        .context("could not search index")
-        .map_err(ise)?;
+    let SearchPage {
+        results,
+        offset,
+        limit,
+    } = match run_search(&state, &query).await {
+        Ok(page) => page,
+        Err(SearchRequestError::BadRequest(message)) => {
+            if query.ty == Some("json".to_string()) {
+                return Ok(HttpResponse::BadRequest().json(ApiError {
+                    kind: "bad_request",
+                    error: &message,
+                }));
+            }
+            return Err(actix_web::error::ErrorBadRequest(message));
+        }
+        Err(SearchRequestError::InvalidQuery(message)) => {
+            if query.ty == Some("json".to_string()) {
+                return Ok(HttpResponse::BadRequest().json(ApiError {
+                    kind: "invalid_query",
+                    error: &message,
+                }));
+            }
 
-    //let results = state.index.send(SearchTantivyIndex(query.query.clone())).await
-    //    .context("could not retrieve search results")
-    //    .map_err(ise)?
-    //    .context("could not search index")
-    //    .map_err(ise)?;
+            let meta = "<meta name=\"robots\" content=\"noindex\">";
+            let resp = render_page(
+                &state.assets.get(),
+                "search",
+                meta,
+                &query.query,
+                Some(&message),
+                &[],
+                &Pagination::default(),
+                &Facets::default(),
+            )
+            .map_err(ise)?;
+
+            return Ok(HttpResponse::BadRequest()
+                .set_header(http::header::CONTENT_TYPE, "text/html")
+                .body(resp));
+        }
+        Err(SearchRequestError::Overloaded) => {
+            return Ok(HttpResponse::ServiceUnavailable()
+                .set_header(http::header::RETRY_AFTER, "1")
+                .finish());
+        }
+        Err(SearchRequestError::Failed(e)) => return Err(ise(e)),
+    };
+    let pagination = Pagination::new(SEARCH_PATH, &query, results.total, offset, limit);
+    let facets = Facets::new(SEARCH_PATH, &query, results.facets, limit);
 
     let mut response = HttpResponse::Ok();
 
     if query.ty == Some("json".to_string()) {
-        Ok(response.json(results))
+        Ok(response.json(SearchResponse {
+            pagination: &pagination,
+            results: &results.cmdlets,
+            facets: &facets,
+        }))
     } else {
         let meta = "<meta name=\"robots\" content=\"noindex\">";
         let resp = render_page(
-            &state.config.web_root,
+            &state.assets.get(),
             "search",
             meta,
             &query.query,
-            &results,
+            None,
+            &results.cmdlets,
+            &pagination,
+            &facets,
         )
         .map_err(ise)?;
         response.set_header(http::header::CONTENT_TYPE, "text/html");
@@ -244,95 +1049,865 @@ async fn search(
     }
 }
 
-fn integrity<P: AsRef<std::path::Path>>(file: P) -> anyhow::Result<String> {
-    use ssri::{Algorithm, IntegrityOpts};
+/// Origin to allow in Access-Control-Allow-Origin for an API request, if the
+/// request's origin is allowed by cors-allowed-origins
+fn cors_origin(config: &Config, request: &HttpRequest) -> Option<http::HeaderValue> {
+    let origin = request.headers().get(http::header::ORIGIN)?;
+    if config.cors_allowed_origins.iter().any(|o| o == "*") {
+        return Some(http::HeaderValue::from_static("*"));
+    }
+
+    let origin_str = origin.to_str().ok()?;
+    if config
+        .cors_allowed_origins
+        .iter()
+        .any(|o| o.eq_ignore_ascii_case(origin_str))
+    {
+        Some(origin.clone())
+    } else {
+        None
+    }
+}
 
-    // TODO probably don't need to do this on every request...
-    let file = File::open(file).context("could not open file")?;
-    let mut reader = BufReader::new(file);
-    let mut content = Vec::new();
-    reader
-        .read_to_end(&mut content)
-        .context("could not read file")?;
+/// Start an API response, with CORS headers when the origin is allowed
+fn api_response(
+    state: &State,
+    request: &HttpRequest,
+    status: http::StatusCode,
+) -> actix_web::dev::HttpResponseBuilder {
+    let mut response = HttpResponse::build(status);
+    response.set_header(http::header::VARY, "origin");
+    if let Some(origin) = cors_origin(&state.config, request) {
+        response.set_header(http::header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    }
 
-    let sha384 = IntegrityOpts::new()
+    response
+}
+
+fn api_error(
+    state: &State,
+    request: &HttpRequest,
+    status: http::StatusCode,
+    kind: &'static str,
+    error: &str,
+) -> HttpResponse {
+    api_response(state, request, status).json(ApiError { kind, error })
+}
+
+/// CORS preflight for API endpoints, which only accept GET
+async fn api_preflight(
+    state: web::Data<State>,
+    request: HttpRequest,
+) -> actix_web::Result<impl Responder> {
+    Ok(api_response(&state, &request, http::StatusCode::NO_CONTENT)
+        .set_header(http::header::ACCESS_CONTROL_ALLOW_METHODS, "GET")
+        .set_header(http::header::ACCESS_CONTROL_MAX_AGE, "86400")
+        .finish())
+}
+
+async fn api_search(
+    state: web::Data<State>,
+    request: HttpRequest,
+) -> actix_web::Result<impl Responder> {
+    let start = std::time::Instant::now();
+    let query = match web::Query::<SearchQuery>::from_query(request.query_string()) {
+        Ok(query) => query,
+        Err(e) => {
+            return Ok(api_error(
+                &state,
+                &request,
+                http::StatusCode::BAD_REQUEST,
+                "bad_request",
+                &e.to_string(),
+            ));
+        }
+    };
+
+    let SearchPage {
+        results,
+        offset,
+        limit,
+    } = match run_search(&state, &query).await {
+        Ok(page) => page,
+        Err(SearchRequestError::BadRequest(message)) => {
+            return Ok(api_error(
+                &state,
+                &request,
+                http::StatusCode::BAD_REQUEST,
+                "bad_request",
+                &message,
+            ));
+        }
+        Err(SearchRequestError::InvalidQuery(message)) => {
+            return Ok(api_error(
+                &state,
+                &request,
+                http::StatusCode::BAD_REQUEST,
+                "invalid_query",
+                &message,
+            ));
+        }
+        Err(SearchRequestError::Overloaded) => {
+            return Ok(
+                api_response(&state, &request, http::StatusCode::SERVICE_UNAVAILABLE)
+                    .set_header(http::header::RETRY_AFTER, "1")
+                    .json(ApiError {
+                        kind: "overloaded",
+                        error: "too many searches in progress, try again shortly",
+                    }),
+            );
+        }
+        Err(SearchRequestError::Failed(e)) => {
+            log::warn!("{:?}", e);
+            return Ok(api_error(
+                &state,
+                &request,
+                http::StatusCode::INTERNAL_SERVER_ERROR,
+                "internal",
+                "search failed",
+            ));
+        }
+    };
+    let pagination = Pagination::new(API_SEARCH_PATH, &query, results.total, offset, limit);
+    let facets = Facets::new(API_SEARCH_PATH, &query, results.facets, limit);
+
+    Ok(
+        api_response(&state, &request, http::StatusCode::OK).json(ApiSearchResponse {
+            query: &query.query,
+            filters: query.filters().into_iter().collect(),
+            page: offset / limit + 1,
+            pagination: &pagination,
+            took_ms: start.elapsed().as_secs_f64() * 1000.0,
+            results: &results.cmdlets,
+            facets: &facets,
+        }),
+    )
+}
+
+async fn openapi(
+    state: web::Data<State>,
+    request: HttpRequest,
+) -> actix_web::Result<impl Responder> {
+    Ok(api_response(&state, &request, http::StatusCode::OK)
+        .set_header(http::header::CONTENT_TYPE, "application/json")
+        .body(OPENAPI))
+}
+
+async fn suggest(
+    state: web::Data<State>,
+    request: HttpRequest,
+) -> actix_web::Result<impl Responder> {
+    let query = web::Query::<SuggestQuery>::from_query(request.query_string())?;
+
+    // Suggestions are requested as the user types, so they share the search
+    // threads and queue limit
+    let _queued = match QueuedSearch::new(&state.queued_searches, state.config.max_queued_searches)
+    {
+        Some(queued) => queued,
+        None => {
+            return Ok(HttpResponse::ServiceUnavailable()
+                .set_header(http::header::RETRY_AFTER, "1")
+                .finish());
+        }
+    };
+    let suggestions = state
+        .search
+        .send(SuggestTantivyIndex {
+            query: query.query.clone(),
+        })
+        .await
+        .context("could not retrieve suggestions")
+        .map_err(ise)?
+        .context("could not complete cmdlet name")
+        .map_err(ise)?;
+
+    Ok(HttpResponse::Ok().json(suggestions))
+}
+
+async fn index_info(
+    state: web::Data<State>,
+    request: HttpRequest,
+) -> actix_web::Result<impl Responder> {
+    Ok(api_response(&state, &request, http::StatusCode::OK).json(&state.index.get().metadata))
+}
+
+/// Compare secrets without exiting early on the first difference, so the
+/// response time doesn't reveal how much of a guessed token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Reload the index from index-dir and the static assets, for deploying them
+/// without a restart. Only enabled when an admin token is configured.
+async fn reload_index(
+    state: web::Data<State>,
+    request: HttpRequest,
+) -> actix_web::Result<impl Responder> {
+    let admin_token = match &state.config.admin_token {
+        Some(admin_token) => admin_token,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let authorized = request
+        .headers()
+        .get(http::header::AUTHORIZATION)
+        .and_then(|v| v.as_bytes().strip_prefix(b"Bearer "))
+        .map(|token| constant_time_eq(token, admin_token.as_bytes()))
+        .unwrap_or(false);
+    if !authorized {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    let current_index = state.index.clone();
+    let current_assets = state.assets.clone();
+    let config = state.config.clone();
+    web::block(move || reload(&current_index, &current_assets, &config))
+        .await
+        .map_err(|e| match e {
+            actix_web::error::BlockingError::Error(e) => ise(e),
+            actix_web::error::BlockingError::Canceled => {
+                ise(anyhow::anyhow!("reload was canceled"))
+            }
+        })?;
+
+    Ok(HttpResponse::Ok().json(&state.index.get().metadata))
+}
+
+fn integrity(content: &[u8]) -> ssri::Integrity {
+    use ssri::{Algorithm, IntegrityOpts};
+
+    IntegrityOpts::new()
         .algorithm(Algorithm::Sha384)
         .chain(content)
         .result()
-        .to_string();
+}
+
+/// Characters of the content hash put in fingerprinted file names
+const FINGERPRINT_LEN: usize = 16;
+
+/// Long lived caching for fingerprinted assets, which never change at a URL
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Encodings build.rs precompresses static files with, in order of
+/// preference, with the extension added to the variant's file name
+const PRECOMPRESSED: &[(&str, &str)] = &[("br", "br"), ("gzip", "gz")];
 
-    Ok(sha384)
+/// Whether the request's Accept-Encoding allows the encoding
+fn accepts_encoding(request: &HttpRequest, encoding: &str) -> bool {
+    let accept_encoding = match request
+        .headers()
+        .get(http::header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(accept_encoding) => accept_encoding,
+        None => return false,
+    };
+
+    accept_encoding.split(',').any(|accepted| {
+        let mut parts = accepted.split(';').map(str::trim);
+        let name = parts.next().unwrap_or("");
+        // q=0 means the encoding is explicitly refused
+        let refused = parts.any(|param| {
+            param.starts_with("q=") && param[2..].parse::<f32>().map(|q| q <= 0.0).unwrap_or(false)
+        });
+
+        name.eq_ignore_ascii_case(encoding) && !refused
+    })
+}
+
+/// Path of the precompressed variant of a file, unless it's missing or older
+/// than the file, as it'd no longer match
+fn precompressed_path(file_path: &path::Path, extension: &str) -> Option<path::PathBuf> {
+    let mut variant_path = file_path.as_os_str().to_owned();
+    variant_path.push(".");
+    variant_path.push(extension);
+
+    let modified = std::fs::metadata(file_path)
+        .and_then(|m| m.modified())
+        .ok()?;
+    let variant_modified = std::fs::metadata(&variant_path)
+        .and_then(|m| m.modified())
+        .ok()?;
+    if variant_modified < modified {
+        log::warn!(
+            "ignoring {}, it's older than the file it was compressed from",
+            path::Path::new(&variant_path).display()
+        );
+        return None;
+    }
+
+    Some(variant_path.into())
+}
+
+/// Read the precompressed variant of a file, if there's a current one
+fn read_precompressed(file_path: &path::Path, extension: &str) -> Option<Vec<u8>> {
+    std::fs::read(precompressed_path(file_path, extension)?).ok()
+}
+
+/// A static file served from memory at a URL including a hash of its
+/// content, eg. /static/style.0123456789abcdef.css, so it can be cached forever
+pub struct Asset {
+    url: String,
+    integrity: String,
+    content_type: &'static str,
+    content: web::Bytes,
+    /// Precompressed content, by encoding
+    compressed: Vec<(&'static str, web::Bytes)>,
+}
+
+impl Asset {
+    fn load(
+        static_dir: &path::Path,
+        file_name: &str,
+        content_type: &'static str,
+    ) -> anyhow::Result<Asset> {
+        let file_path = static_dir.join(file_name);
+        let file = File::open(&file_path)
+            .with_context(|| format!("could not open file: {}", file_path.display()))?;
+        let mut reader = BufReader::new(file);
+        let mut content = Vec::new();
+        reader
+            .read_to_end(&mut content)
+            .with_context(|| format!("could not read file: {}", file_path.display()))?;
+
+        let integrity = integrity(&content);
+        let (_, hex) = integrity.to_hex();
+        let url = match file_name.find('.') {
+            Some(idx) => format!(
+                "/static/{}.{}{}",
+                &file_name[..idx],
+                &hex[..FINGERPRINT_LEN],
+                &file_name[idx..]
+            ),
+            None => format!("/static/{}.{}", file_name, &hex[..FINGERPRINT_LEN]),
+        };
+
+        let compressed = PRECOMPRESSED
+            .iter()
+            .filter_map(|(encoding, extension)| {
+                let content = read_precompressed(&file_path, extension)?;
+                Some((*encoding, content.into()))
+            })
+            .collect();
+
+        Ok(Asset {
+            url,
+            integrity: integrity.to_string(),
+            content_type,
+            content: content.into(),
+            compressed,
+        })
+    }
 }
 
+/// Manifest of the static assets linked from every page, loaded once rather
+/// than on every render
+pub struct Assets {
+    style: Asset,
+    script: Asset,
+}
+
+impl Assets {
+    fn load(web_root: impl AsRef<path::Path>) -> anyhow::Result<Assets> {
+        let static_dir = web_root.as_ref().join("static");
+
+        Ok(Assets {
+            style: Asset::load(&static_dir, "style.css", "text/css; charset=utf-8")
+                .context("could not load stylesheet")?,
+            script: Asset::load(
+                &static_dir,
+                "index.min.js",
+                "application/javascript; charset=utf-8",
+            )
+            .context("could not load javascript")?,
+        })
+    }
+
+    /// Asset with a fingerprinted URL
+    fn find(&self, url: &str) -> Option<&Asset> {
+        [&self.style, &self.script]
+            .iter()
+            .find(|asset| asset.url == url)
+            .copied()
+    }
+}
+
+/// The static assets being served, shared by every worker so they can be
+/// reloaded after a deploy
+#[derive(Clone)]
+struct CurrentAssets(Arc<RwLock<Arc<Assets>>>);
+
+impl CurrentAssets {
+    fn new(assets: Assets) -> CurrentAssets {
+        CurrentAssets(Arc::new(RwLock::new(Arc::new(assets))))
+    }
+
+    fn get(&self) -> Arc<Assets> {
+        self.0.read().expect("current assets lock poisoned").clone()
+    }
+
+    fn reload(&self, web_root: &str) -> anyhow::Result<()> {
+        let assets = Assets::load(web_root)?;
+        *self.0.write().expect("current assets lock poisoned") = Arc::new(assets);
+
+        Ok(())
+    }
+}
+
+/// Serve a fingerprinted asset. Only the current version of each asset is
+/// available, as the old content isn't kept after a reload.
+async fn fingerprinted_asset(
+    state: web::Data<State>,
+    request: HttpRequest,
+) -> actix_web::Result<impl Responder> {
+    let assets = state.assets.get();
+    let asset = match assets.find(request.path()) {
+        Some(asset) => asset,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    let mut response = HttpResponse::Ok();
+    response
+        .set_header(http::header::CONTENT_TYPE, asset.content_type)
+        .set_header(http::header::CACHE_CONTROL, IMMUTABLE_CACHE_CONTROL)
+        .set_header(http::header::VARY, "accept-encoding");
+    let compressed = asset
+        .compressed
+        .iter()
+        .find(|(encoding, _)| accepts_encoding(&request, encoding));
+    match compressed {
+        Some((encoding, content)) => Ok(response
+            .set_header(http::header::CONTENT_ENCODING, *encoding)
+            .body(content.clone())),
+        None => Ok(response
+            .encoding(http::ContentEncoding::Identity)
+            .body(asset.content.clone())),
+    }
+}
+
+/// Serve a file from one of the static directories, using a variant
+/// precompressed by build.rs when the client accepts one. Static files are
+/// never compressed on the fly, that's left for pages and JSON.
+async fn static_file(
+    state: web::Data<State>,
+    request: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> actix_web::Result<HttpResponse> {
+    let (dir, file_name) = path.into_inner();
+    // Hidden files, and .. in particular, are never served
+    if file_name.starts_with('.') {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let file_path = path::Path::new(&state.config.web_root)
+        .join(dir)
+        .join(&file_name);
+    if !file_path.is_file() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    // Variants are served as the file they were compressed from, with their
+    // own ETag, so conditional and range requests work for each encoding
+    let variant = PRECOMPRESSED
+        .iter()
+        .filter(|(encoding, _)| accepts_encoding(&request, encoding))
+        .find_map(|(encoding, extension)| {
+            precompressed_path(&file_path, extension).map(|path| (*encoding, path))
+        });
+    let mut response = match variant {
+        Some((encoding, variant_path)) => {
+            let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let mut response = fs::NamedFile::open(variant_path)?
+                .set_content_type(fs::file_extension_to_mime(extension))
+                .disable_content_disposition()
+                .into_response(&request)?;
+            response.headers_mut().insert(
+                http::header::CONTENT_ENCODING,
+                http::HeaderValue::from_static(encoding),
+            );
+            response
+        }
+        None => {
+            let mut response = fs::NamedFile::open(&file_path)?.into_response(&request)?;
+            response.encoding(http::ContentEncoding::Identity);
+            response
+        }
+    };
+    response.headers_mut().insert(
+        http::header::VARY,
+        http::HeaderValue::from_static("accept-encoding"),
+    );
+
+    Ok(response)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn render_page(
-    web_root: impl AsRef<path::Path>,
+    assets: &Assets,
     body_classes: &str,
     extra_head: &str,
     query_str: &str,
+    error: Option<&str>,
     results: &[CmdletResult],
+    pagination: &Pagination,
+    facets: &Facets,
 ) -> anyhow::Result<Vec<u8>> {
-    let web_root = web_root.as_ref();
-    let style_path = web_root.join("static/style.css");
     let mut resp = Vec::new();
-    let style_integrity =
-        integrity(style_path).context("could not calculate integrity for stylesheet")?;
-    //let script_integrity =
-    //    integrity(js_path).context("could not calculate integrity for javascript")?;
     templates::index_html(
         &mut resp,
-        &style_integrity,
+        assets,
         body_classes,
         extra_head,
         query_str,
+        error,
         results,
+        pagination,
+        facets,
     )
     .context("could not render index template")?;
 
     Ok(resp)
 }
 
+fn render_cmdlet_page(assets: &Assets, cmdlet: &CmdletDetails) -> anyhow::Result<Vec<u8>> {
+    let mut resp = Vec::new();
+    templates::cmdlet_html(&mut resp, assets, cmdlet)
+        .context("could not render cmdlet template")?;
+
+    Ok(resp)
+}
+
+fn render_module_page(assets: &Assets, module: &ModuleDetails) -> anyhow::Result<Vec<u8>> {
+    let mut resp = Vec::new();
+    templates::module_html(&mut resp, assets, module)
+        .context("could not render module template")?;
+
+    Ok(resp)
+}
+
 async fn index(
     state: web::Data<State>,
     _request: HttpRequest,
 ) -> actix_web::Result<impl Responder> {
-    let resp = render_page(&state.config.web_root, "", "", "", &[]).map_err(ise)?;
+    let resp = render_page(
+        &state.assets.get(),
+        "",
+        "",
+        "",
+        None,
+        &[],
+        &Pagination::default(),
+        &Facets::default(),
+    )
+    .map_err(ise)?;
+
+    Ok(HttpResponse::Ok()
+        .set_header(http::header::CONTENT_TYPE, "text/html")
+        .body(resp))
+}
+
+async fn cmdlet(
+    state: web::Data<State>,
+    path: web::Path<(String, String, String)>,
+) -> actix_web::Result<impl Responder> {
+    let (module_name, module_version, name) = path.into_inner();
+    let served = state.index.get();
+    let details = find_cmdlet(&served, &module_name, &module_version, &name)
+        .context("could not find cmdlet")
+        .map_err(ise)?;
+
+    let details = match details {
+        Some(details) => details,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let resp = render_cmdlet_page(&state.assets.get(), &details).map_err(ise)?;
 
     Ok(HttpResponse::Ok()
         .set_header(http::header::CONTENT_TYPE, "text/html")
         .body(resp))
 }
 
+async fn module_page(
+    state: web::Data<State>,
+    module_name: &str,
+    module_version: Option<&str>,
+) -> actix_web::Result<HttpResponse> {
+    let served = state.index.get();
+    let details = find_module(&served, module_name, module_version)
+        .context("could not find module")
+        .map_err(ise)?;
+
+    let details = match details {
+        Some(details) => details,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let resp = render_module_page(&state.assets.get(), &details).map_err(ise)?;
+
+    Ok(HttpResponse::Ok()
+        .set_header(http::header::CONTENT_TYPE, "text/html")
+        .body(resp))
+}
+
+async fn module(
+    state: web::Data<State>,
+    path: web::Path<(String,)>,
+) -> actix_web::Result<impl Responder> {
+    module_page(state, &path.0, None).await
+}
+
+async fn module_version(
+    state: web::Data<State>,
+    path: web::Path<(String, String)>,
+) -> actix_web::Result<impl Responder> {
+    module_page(state, &path.0, Some(&path.1)).await
+}
+
 async fn robots() -> actix_web::Result<impl Responder> {
     let robots = "User-Agent: *
-Disallow: /search";
+Disallow: /search
+Disallow: /suggest
+Disallow: /api/
+Disallow: /admin/";
     Ok(HttpResponse::Ok()
         .set_header(http::header::CONTENT_TYPE, "text/plain")
         .body(robots))
 }
 
 struct State {
-    index: tantivy::Index,
-    //index: actix::prelude::Addr<TantivyIndexExecutor>,
+    index: CurrentIndex,
+    assets: CurrentAssets,
+    search: Addr<TantivyIndexExecutor>,
+    /// Searches sent to the search threads which haven't finished, shared by
+    /// every worker
+    queued_searches: Arc<AtomicUsize>,
     config: Config,
 }
 
-struct TantivyIndexExecutor(tantivy::Index);
+/// A search counted against the queue limit until it's dropped
+struct QueuedSearch<'a>(&'a AtomicUsize);
+
+impl<'a> QueuedSearch<'a> {
+    fn new(queued_searches: &'a AtomicUsize, max_queued_searches: usize) -> Option<Self> {
+        if queued_searches.fetch_add(1, Ordering::SeqCst) >= max_queued_searches {
+            queued_searches.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+
+        Some(QueuedSearch(queued_searches))
+    }
+}
+
+impl<'a> Drop for QueuedSearch<'a> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// An opened index which has been checked against the schema
+struct ServedIndex {
+    /// Directory the index was opened from, with symlinks resolved
+    path: path::PathBuf,
+    index: tantivy::Index,
+    /// Shared by every request, as creating a reader per search is slow.
+    /// Searchers come from its pool, sized to the number of CPUs.
+    reader: tantivy::IndexReader,
+    schema: CmdletSchema,
+    metadata: IndexMetadata,
+}
+
+impl ServedIndex {
+    fn open(index_dir: &str) -> anyhow::Result<ServedIndex> {
+        // index-dir may be a symlink to the current index, so resolve it to
+        // notice when it's pointed somewhere else
+        let path = std::fs::canonicalize(index_dir)
+            .with_context(|| format!("could not resolve index directory: {}", index_dir))?;
+
+        // Check the index before opening it, as a mismatched schema would
+        // otherwise only show up as errors when searching
+        let metadata = IndexMetadata::read(&path)
+            .context("could not read index metadata, rebuild the index with the indexer")?;
+        metadata
+            .check_compatible()
+            .context("index is incompatible, rebuild it with the indexer")?;
+
+        let index = tantivy::Index::open_in_dir(&path)
+            .map_err(SearchError::Tantivy)
+            .context("failed to open index directory")?;
+        pascal_splitter::register(&index);
+        let schema = CmdletSchema::for_index(&index)
+            .context("index was not built with the current schema, rebuild it with the indexer")?;
+        let reader = index
+            .reader()
+            .map_err(SearchError::Tantivy)
+            .context("could not get reader for index")?;
+
+        log::info!(
+            "Opened index {} built {} by indexer {}, with {} cmdlets from {} modules",
+            path.display(),
+            metadata.built_at,
+            metadata.indexer_version,
+            metadata.cmdlets,
+            metadata.modules
+        );
+
+        Ok(ServedIndex {
+            path,
+            index,
+            reader,
+            schema,
+            metadata,
+        })
+    }
+}
+
+/// The index being served, shared by every worker so it can be swapped for a
+/// newly deployed one while running
+#[derive(Clone)]
+struct CurrentIndex {
+    served: Arc<RwLock<Arc<ServedIndex>>>,
+    /// Held while reloading, so concurrent reloads don't open the index twice
+    reloading: Arc<Mutex<()>>,
+}
+
+impl CurrentIndex {
+    fn new(served: ServedIndex) -> CurrentIndex {
+        CurrentIndex {
+            served: Arc::new(RwLock::new(Arc::new(served))),
+            reloading: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Index to use for a request. Requests keep using the index they started
+    /// with if it's swapped part way through.
+    fn get(&self) -> Arc<ServedIndex> {
+        self.served
+            .read()
+            .expect("current index lock poisoned")
+            .clone()
+    }
+
+    /// Open the index index-dir now points at and swap it in, if it has been
+    /// replaced. A new index which fails to open or validate is not served.
+    fn reload(&self, index_dir: &str) -> anyhow::Result<()> {
+        let _reloading = self.reloading.lock().expect("reload lock poisoned");
+
+        let current = self.get();
+        let path = std::fs::canonicalize(index_dir)
+            .with_context(|| format!("could not resolve index directory: {}", index_dir))?;
+        let metadata = IndexMetadata::read(&path).context("could not read index metadata")?;
+        if path == current.path && metadata == current.metadata {
+            return Ok(());
+        }
+
+        let served = ServedIndex::open(index_dir)?;
+        *self.served.write().expect("current index lock poisoned") = Arc::new(served);
+        log::info!("Swapped in index {}", path.display());
+
+        Ok(())
+    }
+
+    /// Check for a new index every interval, in a background thread
+    fn poll(&self, index_dir: String, interval: Duration) {
+        let current_index = self.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            if let Err(e) = current_index.reload(&index_dir) {
+                log::warn!("{:?}", e.context("could not reload index"));
+            }
+        });
+    }
+}
+
+/// Reload the index and static assets, after either has been redeployed
+fn reload(
+    current_index: &CurrentIndex,
+    current_assets: &CurrentAssets,
+    config: &Config,
+) -> anyhow::Result<()> {
+    // Try both, so a bad index doesn't stop new assets being served
+    let index = current_index
+        .reload(&config.index_dir)
+        .context("could not reload index");
+    let assets = current_assets
+        .reload(&config.web_root)
+        .context("could not reload static assets");
+
+    index.and(assets)
+}
+
+/// Reload whenever the process receives SIGHUP
+fn reload_on_hangup(current_index: CurrentIndex, current_assets: CurrentAssets, config: Config) {
+    use actix_rt::signal::unix::{signal, SignalKind};
+
+    // Signals have to be registered from inside the runtime
+    actix_rt::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                log::warn!("could not listen for hangup signal: {:?}", e);
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            log::info!("Received hangup, reloading index and static assets");
+            let current_index = current_index.clone();
+            let current_assets = current_assets.clone();
+            let config = config.clone();
+            // Opening an index blocks, so keep it off the event loop
+            std::thread::spawn(move || {
+                if let Err(e) = reload(&current_index, &current_assets, &config) {
+                    log::warn!("{:?}", e);
+                }
+            });
+        }
+    });
+}
+
+/// Runs searches and suggestions on a SyncArbiter's threads, off the HTTP
+/// workers
+struct TantivyIndexExecutor(CurrentIndex);
 
 impl actix::prelude::Actor for TantivyIndexExecutor {
     type Context = actix::prelude::SyncContext<Self>;
 }
 
-struct SearchTantivyIndex(String);
+struct SearchTantivyIndex {
+    query: String,
+    filters: Vec<(&'static str, String)>,
+    offset: usize,
+    limit: usize,
+}
 
 impl Message for SearchTantivyIndex {
-    type Result = anyhow::Result<Vec<CmdletResult>>;
+    type Result = anyhow::Result<SearchResults>;
 }
 
 impl Handler<SearchTantivyIndex> for TantivyIndexExecutor {
-    type Result = anyhow::Result<Vec<CmdletResult>>;
+    type Result = anyhow::Result<SearchResults>;
 
     fn handle(&mut self, query: SearchTantivyIndex, _: &mut Self::Context) -> Self::Result {
-        search_index(&self.0, &query.0)
+        let filters = query
+            .filters
+            .iter()
+            .map(|(facet, value)| (*facet, value.as_str()))
+            .collect::<Vec<_>>();
+        let served = self.0.get();
+        search_index(&served, &query.query, &filters, query.offset, query.limit)
+    }
+}
+
+struct SuggestTantivyIndex {
+    query: String,
+}
+
+impl Message for SuggestTantivyIndex {
+    type Result = anyhow::Result<Vec<String>>;
+}
+
+impl Handler<SuggestTantivyIndex> for TantivyIndexExecutor {
+    type Result = anyhow::Result<Vec<String>>;
+
+    fn handle(&mut self, query: SuggestTantivyIndex, _: &mut Self::Context) -> Self::Result {
+        let served = self.0.get();
+        suggest_index(&served, &query.query)
     }
 }
 
@@ -351,9 +1926,22 @@ fn error_handler(
    |        |
 
 Something went wrong...";
+    // API clients get their JSON errors as they are
+    let is_json = response
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/json"))
+        .unwrap_or(false);
+    if is_json || response.request().path().starts_with("/api/") {
+        return Ok(middleware::errhandlers::ErrorHandlerResponse::Response(
+            response,
+        ));
+    }
+
     Ok(middleware::errhandlers::ErrorHandlerResponse::Response(
         response.map_body(|head, _body| {
-            head.headers_mut().append(
+            head.headers_mut().insert(
                 http::header::CONTENT_TYPE,
                 http::HeaderValue::from_static("text/plain"),
             );
@@ -363,15 +1951,27 @@ Something went wrong...";
 }
 
 fn run_server(config: &Config) -> anyhow::Result<()> {
-    let t_index = tantivy::Index::open_in_dir(&config.index_dir)
-        .map_err(SearchError::Tantivy)
-        .context("failed to open index directory")?;
-    pascal_splitter::register(&t_index);
+    let current_index = CurrentIndex::new(ServedIndex::open(&config.index_dir)?);
+    let current_assets =
+        CurrentAssets::new(Assets::load(&config.web_root).context("could not load static assets")?);
+    reload_on_hangup(
+        current_index.clone(),
+        current_assets.clone(),
+        config.clone(),
+    );
+    if config.index_poll_interval > 0 {
+        current_index.poll(
+            config.index_dir.clone(),
+            Duration::from_secs(config.index_poll_interval),
+        );
+    }
 
-    //let todo = 1; // TODO should be num_cpu
-    //let index_addr = SyncArbiter::start(todo, move || {
-    //    TantivyIndexExecutor(t_index.clone())
-    //});
+    let search_threads = config.search_threads.unwrap_or_else(num_cpus::get);
+    let executor_index = current_index.clone();
+    let search_addr = SyncArbiter::start(search_threads, move || {
+        TantivyIndexExecutor(executor_index.clone())
+    });
+    let queued_searches = Arc::new(AtomicUsize::new(0));
 
     let server_config = config.clone();
 
@@ -397,8 +1997,10 @@ fn run_server(config: &Config) -> anyhow::Result<()> {
 
         App::new()
             .data(State {
-                index: t_index.clone(),
-                //index: index_addr.clone(),
+                index: current_index.clone(),
+                assets: current_assets.clone(),
+                search: search_addr.clone(),
+                queued_searches: queued_searches.clone(),
                 config: server_config.clone(),
             })
             .wrap(
@@ -415,7 +2017,27 @@ fn run_server(config: &Config) -> anyhow::Result<()> {
             .wrap(default_headers)
             .route("/", web::get().to(index))
             .route("/search", web::get().to(search))
+            .route("/suggest", web::get().to(suggest))
+            .route("/api/index-info", web::get().to(index_info))
+            .route(API_SEARCH_PATH, web::get().to(api_search))
+            .route(
+                API_SEARCH_PATH,
+                web::method(http::Method::OPTIONS).to(api_preflight),
+            )
+            .route("/api/v1/openapi.json", web::get().to(openapi))
+            .route("/admin/reload-index", web::post().to(reload_index))
+            .route("/cmdlet/{module}/{version}/{name}", web::get().to(cmdlet))
+            .route("/module/{module}", web::get().to(module))
+            .route("/module/{module}/{version}", web::get().to(module_version))
             .route("/robots.txt", web::get().to(robots))
+            .route(
+                "/static/{name:[^/.]+}.{fingerprint:[0-9a-f]+}.{extension:[^/]+}",
+                web::get().to(fingerprinted_asset),
+            )
+            .route(
+                "/{dir:static|assets|typescript|sass}/{file}",
+                web::get().to(static_file),
+            )
             .service(fs::Files::new("/static", static_dir))
             .service(fs::Files::new("/assets", assets_dir))
             .service(fs::Files::new("/typescript", typescript_dir))
@@ -482,6 +2104,43 @@ struct Config {
     listen_addr: Option<String>,
     ssl: Option<SslConfig>,
     headers: std::collections::HashMap<String, String>,
+    /// Largest number of results a single search can return
+    #[serde(default = "default_max_results")]
+    max_results: usize,
+    /// Largest offset into the results a search can request
+    #[serde(default = "default_max_offset")]
+    max_offset: usize,
+    /// Seconds between checks for a new index in index-dir, or 0 to only
+    /// reload on SIGHUP or through the admin endpoint
+    #[serde(default = "default_index_poll_interval")]
+    index_poll_interval: u64,
+    /// Threads to run searches on, defaulting to the number of CPUs
+    search_threads: Option<usize>,
+    /// Searches which can be waiting for a search thread before new ones are
+    /// refused with a 503
+    #[serde(default = "default_max_queued_searches")]
+    max_queued_searches: usize,
+    /// Origins allowed to call the JSON API from a browser, or "*" for any
+    #[serde(default)]
+    cors_allowed_origins: Vec<String>,
+    /// Bearer token for admin endpoints, which are disabled without one
+    admin_token: Option<String>,
+}
+
+fn default_max_results() -> usize {
+    100
+}
+
+fn default_max_offset() -> usize {
+    1000
+}
+
+fn default_index_poll_interval() -> u64 {
+    60
+}
+
+fn default_max_queued_searches() -> usize {
+    64
 }
 
 impl Config {
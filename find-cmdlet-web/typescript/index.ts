@@ -4,16 +4,69 @@ function htmlEncode(text: string): string {
     return el.innerHTML;
 }
 
-function displayJson(json: [any]) {
+function detailUrl(cmdlet: any): string {
+    return '/cmdlet/' + [cmdlet.module_name, cmdlet.module_version, cmdlet.name]
+        .map(encodeURIComponent)
+        .join('/');
+}
+
+function moduleUrl(cmdlet: any): string {
+    return '/module/' + [cmdlet.module_name, cmdlet.module_version]
+        .map(encodeURIComponent)
+        .join('/');
+}
+
+// The links in the JSON point at the JSON API, the page itself is served as HTML
+function htmlUrl(url: string): string {
+    return url.replace(/&t=json$/, '');
+}
+
+function displayFacets(json: any) {
+    const facets = document.querySelector<HTMLElement>('#facets');
+    if (!facets) {
+        return;
+    }
+
+    const titles: {[name: string]: string} = {
+        module: 'Module',
+        tag: 'Tag',
+        verb: 'Verb',
+        source: 'Source',
+    };
+    let facetHTML = '';
+    for (const name of Object.keys(json.facets || {})) {
+        const values = json.facets[name];
+        if (values.length === 0) {
+            continue;
+        }
+
+        const items = values.map((v: any) =>
+            `<li class="${v.selected ? 'selected' : ''}"><a href="${htmlEncode(htmlUrl(v.url))}">${htmlEncode(v.value)}</a> <span class="count">${v.count}</span></li>`
+        ).join('');
+        facetHTML += `<div class="facet"><h3>${htmlEncode(titles[name] || name)}</h3><ul>${items}</ul></div>`;
+    }
+
+    facets.innerHTML = facetHTML;
+}
+
+function displayJson(json: any) {
     const results = document.querySelector<HTMLElement>('#results')!;
     let resultHTML = '';
-    for (let cmdlet of json) {
+    if (json.results.length > 0) {
+        resultHTML += `<div class="result_count">
+            Showing ${json.offset + 1}&ndash;${json.offset + json.results.length} of ${json.total}
+        </div>`;
+    }
+    for (let cmdlet of json.results) {
         const tags = cmdlet.tags.map((t: any) => `<li>${htmlEncode(t)}</li>`).join('');
+        const alias = cmdlet.matched_alias
+            ? `<div class="alias">${htmlEncode(cmdlet.matched_alias)} &rarr;</div>`
+            : '';
         const template = `
 <div class="result">
-    <div class="name"><a href="${cmdlet.url}">${htmlEncode(cmdlet.name)}</a></div>
+    ${alias}<div class="name"><a href="${detailUrl(cmdlet)}">${htmlEncode(cmdlet.name)}</a></div>
     <div class="module">
-        <span class="mod_name">${htmlEncode(cmdlet.module_name)}</span>
+        <span class="mod_name"><a href="${moduleUrl(cmdlet)}">${htmlEncode(cmdlet.module_name)}</a></span>
         (<span class="mod_version">${htmlEncode(cmdlet.module_version)}</span>)
     </div>
     <div class="tags"><ul>${tags}</ul></div>
@@ -30,11 +83,123 @@ function displayJson(json: [any]) {
         resultHTML += template;
     }
 
+    if (json.prev || json.next) {
+        resultHTML += '<div class="pagination">';
+        if (json.prev) {
+            resultHTML += `<a class="prev" rel="prev" href="${htmlEncode(htmlUrl(json.prev))}">Previous</a>`;
+        }
+        if (json.next) {
+            resultHTML += `<a class="next" rel="next" href="${htmlEncode(htmlUrl(json.next))}">Next</a>`;
+        }
+        resultHTML += '</div>';
+    }
+
     results.innerHTML = resultHTML;
+    displayFacets(json);
     const body = document.querySelector<HTMLBodyElement>('body');
     body?.classList.add('search');
 }
 
+// Delay after typing stops before fetching suggestions, in milliseconds
+const SUGGEST_DELAY = 150;
+
+function setupSuggestions(searchForm: HTMLFormElement) {
+    const input = searchForm.querySelector<HTMLInputElement>('input[name=q]');
+    if (!input) {
+        return;
+    }
+
+    const list = document.createElement('ul');
+    list.id = 'suggestions';
+    list.hidden = true;
+    input.insertAdjacentElement('afterend', list);
+
+    let timer: number | undefined;
+    let selected = -1;
+    // Responses can arrive out of order, only the latest request is shown
+    let latest = 0;
+
+    const hide = () => {
+        window.clearTimeout(timer);
+        latest += 1;
+        selected = -1;
+        list.hidden = true;
+        list.innerHTML = '';
+    };
+
+    const choose = (name: string) => {
+        input.value = name;
+        hide();
+        searchForm.dispatchEvent(new Event('submit', {cancelable: true}));
+    };
+
+    const highlight = (index: number) => {
+        const items = list.querySelectorAll('li');
+        items.forEach((item, i) => item.classList.toggle('selected', i === index));
+        selected = index;
+    };
+
+    input.addEventListener('input', () => {
+        const query = input.value.trim();
+        hide();
+        if (!query) {
+            return;
+        }
+
+        const request = latest;
+        timer = window.setTimeout(async () => {
+            try {
+                const response = await fetch('/suggest?q=' + encodeURIComponent(query));
+                if (!response.ok) {
+                    throw '';
+                }
+
+                const names: string[] = await response.json();
+                if (request !== latest) {
+                    return;
+                }
+
+                list.innerHTML = names.map(name => `<li>${htmlEncode(name)}</li>`).join('');
+                list.hidden = names.length === 0;
+            } catch {
+                hide();
+            }
+        }, SUGGEST_DELAY);
+    });
+
+    input.addEventListener('keydown', (event: KeyboardEvent) => {
+        const count = list.querySelectorAll('li').length;
+        if (list.hidden || count === 0) {
+            return;
+        }
+
+        if (event.key === 'ArrowDown') {
+            event.preventDefault();
+            highlight((selected + 1) % count);
+        } else if (event.key === 'ArrowUp') {
+            event.preventDefault();
+            highlight((selected + count - 1) % count);
+        } else if (event.key === 'Enter' && selected >= 0) {
+            event.preventDefault();
+            choose(list.querySelectorAll('li')[selected].textContent || '');
+        } else if (event.key === 'Escape') {
+            hide();
+        }
+    });
+
+    // mousedown rather than click, so the input doesn't lose focus first
+    list.addEventListener('mousedown', (event: MouseEvent) => {
+        const item = (event.target as HTMLElement).closest('li');
+        if (item) {
+            event.preventDefault();
+            choose(item.textContent || '');
+        }
+    });
+
+    input.addEventListener('blur', hide);
+    searchForm.addEventListener('submit', hide);
+}
+
 function main() {
     const searchForm = document.forms.namedItem('search');
 
@@ -51,6 +216,10 @@ function main() {
             body?.classList.remove('search')
             const results = document.querySelector<HTMLElement>('#results')!;
             results.innerHTML = '';
+            const facets = document.querySelector<HTMLElement>('#facets');
+            if (facets) {
+                facets.innerHTML = '';
+            }
         }
     };
 
@@ -67,7 +236,7 @@ function main() {
                 throw '';
             }
 
-            let json: [any] = await response.json();
+            let json: any = await response.json();
 
             displayJson(json);
 
@@ -78,6 +247,9 @@ function main() {
         }
     });
 
+    if (searchForm) {
+        setupSuggestions(searchForm);
+    }
 }
 
 main();
\ No newline at end of file
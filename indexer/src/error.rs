@@ -15,6 +15,7 @@ pub enum FindCmdletError {
     MissingHelpDir(String, String, String),
     MissingCommandFileName(String, String, String),
     MissingHelpText(String, String, String, String),
+    MissingNuspec(String),
     TantivyError(tantivy::TantivyError),
 }
 
@@ -54,6 +55,9 @@ impl Display for FindCmdletError {
                     command, name, version, file
                 ))
             }
+            FindCmdletError::MissingNuspec(file) => {
+                f.write_fmt(format_args!("Missing .nuspec file in package: {}", file))
+            }
             FindCmdletError::TantivyError(te) => te.fmt(f),
         }
     }
@@ -1,102 +1,247 @@
-use crate::cmdlet::Cmdlet;
 use crate::error::FindCmdletError;
-use find_cmdlet_index::pascal_splitter;
-use std::{fs::DirBuilder, path::Path};
-use tantivy::doc;
+use find_cmdlet_index::{
+    cmdlet::Cmdlet,
+    metadata::{IndexMetadata, SCHEMA_VERSION},
+    pascal_splitter,
+    schema::{module_key, CmdletSchema},
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::DirBuilder,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
 
 pub struct Indexer {
+    directory: PathBuf,
+    index: tantivy::Index,
     writer: tantivy::IndexWriter,
+    schema: CmdletSchema,
 
-    module_field: tantivy::schema::Field,
-    module_version_field: tantivy::schema::Field,
-    name_field: tantivy::schema::Field,
-    url_field: tantivy::schema::Field,
-    tags_field: tantivy::schema::Field,
-    synopsis_field: tantivy::schema::Field,
-    syntax_field: tantivy::schema::Field,
-    description_field: tantivy::schema::Field,
-    notes_field: tantivy::schema::Field,
+    /// Reader for the index as it was when opened, when updating incrementally
+    existing: Option<tantivy::IndexReader>,
+
+    /// Versions of each module, by module id, that have been checked against
+    /// the existing index, and whether they need indexing
+    replaced_modules: Mutex<HashMap<String, HashMap<String, bool>>>,
 }
 
 impl Indexer {
-    pub fn new(directory: impl AsRef<Path>) -> anyhow::Result<Indexer> {
-        let mut schema_builder = tantivy::schema::SchemaBuilder::default();
+    /// Create an indexer for the given directory. When incremental, an existing
+    /// index in the directory is updated rather than replaced
+    pub fn new(directory: impl AsRef<Path>, incremental: bool) -> anyhow::Result<Indexer> {
+        let schema = CmdletSchema::new();
 
-        let indexed_text_options = tantivy::schema::TextOptions::default().set_indexing_options(
-            tantivy::schema::TextFieldIndexing::default()
-                .set_index_option(tantivy::schema::IndexRecordOption::WithFreqsAndPositions)
-                .set_tokenizer("en_stem"),
-        );
-        let stored_text_options = indexed_text_options.clone().set_stored();
-
-        let cmdlet_name_options = tantivy::schema::TextOptions::default()
-            .set_stored()
-            .set_indexing_options(
-                tantivy::schema::TextFieldIndexing::default()
-                    .set_index_option(tantivy::schema::IndexRecordOption::Basic)
-                    .set_tokenizer("pascal"),
-            );
-
-        let module_name = schema_builder.add_text_field(
-            "module_name",
-            tantivy::schema::TEXT | tantivy::schema::STORED,
-        );
-        let module_version =
-            schema_builder.add_text_field("module_version", tantivy::schema::STORED);
-        let name = schema_builder.add_text_field("name", cmdlet_name_options);
-        let url = schema_builder.add_text_field("url", tantivy::schema::STORED);
-        let tags =
-            schema_builder.add_text_field("tags", tantivy::schema::TEXT | tantivy::schema::STORED);
-        let synopsis = schema_builder.add_text_field("synopsis", stored_text_options);
-        let syntax = schema_builder
-            .add_text_field("syntax", tantivy::schema::TEXT | tantivy::schema::STORED);
-        let description =
-            schema_builder.add_text_field("description", indexed_text_options.clone());
-        let notes = schema_builder.add_text_field("notes", indexed_text_options);
-
-        let schema = schema_builder.build();
         DirBuilder::new().recursive(true).create(&directory)?;
-        let index = tantivy::Index::create_in_dir(&directory, schema)
-            .map_err(FindCmdletError::TantivyError)?;
+        let index = if incremental {
+            let mmap_directory = tantivy::directory::MmapDirectory::open(&directory)
+                .map_err(|e| FindCmdletError::TantivyError(e.into()))?;
+            tantivy::Index::open_or_create(mmap_directory, schema.schema())
+        } else {
+            tantivy::Index::create_in_dir(&directory, schema.schema())
+        }
+        .map_err(FindCmdletError::TantivyError)?;
 
         pascal_splitter::register(&index);
 
+        let existing = if incremental {
+            Some(index.reader().map_err(FindCmdletError::TantivyError)?)
+        } else {
+            None
+        };
+
         let index_writer = index
             .writer(4_000_000_000)
             .map_err(FindCmdletError::TantivyError)?;
 
         Ok(Indexer {
+            directory: directory.as_ref().to_path_buf(),
+            index,
             writer: index_writer,
-
-            module_field: module_name,
-            module_version_field: module_version,
-            name_field: name,
-            url_field: url,
-            tags_field: tags,
-            synopsis_field: synopsis,
-            syntax_field: syntax,
-            description_field: description,
-            notes_field: notes,
+            schema,
+            existing,
+            replaced_modules: Mutex::new(HashMap::new()),
         })
     }
 
     pub fn update(&self, cmdlet: &Cmdlet) {
-        self.writer.add_document(doc!(
-            self.module_field => cmdlet.module.clone(),
-            self.module_version_field => cmdlet.module_version.clone(),
-            self.name_field => cmdlet.name.clone(),
-            self.url_field => cmdlet.url.clone(),
-            self.tags_field => cmdlet.tags.join(" "),
-            self.synopsis_field => cmdlet.synopsis.clone(),
-            self.syntax_field => cmdlet.syntax.clone(),
-            self.description_field => cmdlet.description.clone(),
-            self.notes_field => cmdlet.notes.clone(),
-        ));
+        match self.schema.to_document(cmdlet) {
+            Ok(document) => {
+                self.writer.add_document(document);
+            }
+            Err(e) => log::warn!("could not create document for {}: {:?}", cmdlet.name, e),
+        }
     }
 
+    /// Prepare to index a version of a module, deleting any other versions of
+    /// the module from the existing index, or indexed earlier in this run.
+    /// Returns false if this version is already indexed, so the module can be
+    /// skipped.
+    pub fn replace_module(&self, module: &str, version: &str) -> anyhow::Result<bool> {
+        let reader = match &self.existing {
+            Some(reader) => reader,
+            None => return Ok(true),
+        };
+
+        let mut replaced_modules = self
+            .replaced_modules
+            .lock()
+            .expect("replaced modules lock poisoned");
+        let versions = replaced_modules.entry(module.to_lowercase()).or_default();
+        if let Some(&replace) = versions.get(&version.to_lowercase()) {
+            return Ok(replace);
+        }
+
+        let searcher = reader.searcher();
+        let query = tantivy::query::TermQuery::new(
+            tantivy::Term::from_field_text(self.schema.module_id, &module.to_lowercase()),
+            tantivy::schema::IndexRecordOption::Basic,
+        );
+        let count = searcher
+            .search(&query, &tantivy::collector::Count)
+            .map_err(FindCmdletError::TantivyError)?;
+        let docs = if count > 0 {
+            searcher
+                .search(&query, &tantivy::collector::TopDocs::with_limit(count))
+                .map_err(FindCmdletError::TantivyError)?
+        } else {
+            vec![]
+        };
+
+        let mut indexed_versions = HashSet::new();
+        for (_, doc_addr) in docs {
+            let doc = searcher
+                .doc(doc_addr)
+                .map_err(FindCmdletError::TantivyError)?;
+            if let Some(indexed_version) = doc
+                .get_first(self.schema.module_version)
+                .and_then(|v| v.text())
+            {
+                indexed_versions.insert(indexed_version.to_string());
+            }
+        }
+
+        let replace = !indexed_versions
+            .iter()
+            .any(|v| v.eq_ignore_ascii_case(version));
+        if replace {
+            // Versions added earlier in this run are replaced too, and skipped
+            // if they're seen again
+            let added_versions = versions
+                .iter_mut()
+                .filter(|(_, replace)| **replace)
+                .map(|(added_version, replace)| {
+                    *replace = false;
+                    added_version.clone()
+                })
+                .collect::<Vec<_>>();
+            for indexed_version in indexed_versions.into_iter().chain(added_versions) {
+                log::info!("Removing {} [{}] from index", module, indexed_version);
+                self.writer.delete_term(tantivy::Term::from_field_text(
+                    self.schema.module_key,
+                    &module_key(module, &indexed_version),
+                ));
+            }
+        }
+        versions.insert(version.to_lowercase(), replace);
+
+        Ok(replace)
+    }
+
+    /// Commit the index, then stamp it with metadata describing what's in it
     pub fn commit(&mut self) -> anyhow::Result<u64> {
-        self.writer
+        let opstamp = self
+            .writer
             .commit()
-            .map_err(|e| FindCmdletError::TantivyError(e).into())
+            .map_err(FindCmdletError::TantivyError)?;
+
+        self.metadata()?.write(&self.directory)?;
+
+        Ok(opstamp)
+    }
+
+    fn metadata(&self) -> anyhow::Result<IndexMetadata> {
+        // Counted from the committed index, so incremental updates include
+        // the cmdlets which were already indexed
+        let reader = self.index.reader().map_err(FindCmdletError::TantivyError)?;
+        let searcher = reader.searcher();
+
+        let mut facet_collector = tantivy::collector::FacetCollector::for_field(self.schema.facets);
+        facet_collector.add_facet("/module");
+        facet_collector.add_facet("/source");
+        let facet_counts = searcher
+            .search(&tantivy::query::AllQuery, &facet_collector)
+            .map_err(FindCmdletError::TantivyError)?;
+
+        let sources = facet_counts
+            .get("/source")
+            .filter_map(|(facet, count)| Some((facet.to_path().last()?.to_string(), count)))
+            .collect::<BTreeMap<_, _>>();
+
+        Ok(IndexMetadata {
+            schema_version: SCHEMA_VERSION,
+            indexer_version: env!("CARGO_PKG_VERSION").to_string(),
+            built_at: chrono::Utc::now(),
+            cmdlets: searcher.num_docs(),
+            modules: facet_counts.get("/module").count() as u64,
+            sources,
+        })
     }
 }
+
+#[test]
+fn incremental_replaces_versions() {
+    let directory =
+        std::env::temp_dir().join(format!("find-cmdlet-incremental-{}", std::process::id()));
+    let cmdlet = |version: &str| Cmdlet {
+        module: "Things".to_string(),
+        module_version: version.to_string(),
+        name: "Get-Thing".to_string(),
+        aliases: Vec::new(),
+        url: String::new(),
+        tags: Vec::new(),
+        synopsis: String::new(),
+        syntax: String::new(),
+        description: String::new(),
+        notes: String::new(),
+        parameters: Vec::new(),
+        examples: Vec::new(),
+        links: Vec::new(),
+        module_info: Default::default(),
+        source: find_cmdlet_index::cmdlet::Source::PSGallery,
+    };
+
+    let mut indexer = Indexer::new(&directory, false).unwrap();
+    indexer.update(&cmdlet("1.0"));
+    indexer.commit().unwrap();
+    drop(indexer);
+
+    // Two new versions in one run only leave the last one indexed
+    let mut indexer = Indexer::new(&directory, true).unwrap();
+    for version in &["1.1", "1.2"] {
+        assert!(indexer.replace_module("Things", version).unwrap());
+        indexer.update(&cmdlet(version));
+    }
+    assert!(!indexer.replace_module("Things", "1.1").unwrap());
+    indexer.commit().unwrap();
+
+    let reader = indexer.index.reader().unwrap();
+    let searcher = reader.searcher();
+    let versions = searcher
+        .search(
+            &tantivy::query::AllQuery,
+            &tantivy::collector::TopDocs::with_limit(10),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|(_, doc_addr)| {
+            let doc = searcher.doc(doc_addr).unwrap();
+            let version = doc.get_first(indexer.schema.module_version).unwrap();
+            version.text().unwrap().to_string()
+        })
+        .collect::<Vec<_>>();
+    drop(indexer);
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(versions, vec!["1.2"]);
+}
@@ -1,10 +1,16 @@
 use clap::{crate_authors, crate_description, crate_name, crate_version, App, Arg, ArgGroup};
 
-mod cmdlet;
 mod error;
 mod indexer;
+mod maml;
+mod manifest;
 mod markdown;
+mod nupkg;
+mod psd1;
 mod psgallery;
+mod script;
+mod syntax;
+mod text;
 
 use indexer::Indexer;
 
@@ -21,6 +27,31 @@ fn main() -> anyhow::Result<()> {
                 .takes_value(true)
                 .multiple(true),
         )
+        .arg(
+            Arg::with_name("markdown-module-name")
+                .long("markdown-module-name")
+                .help("Module name for markdown files, overriding the module page name")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("markdown-module-version")
+                .long("markdown-module-version")
+                .help("Module version for markdown files, overriding the module page help version")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("maml-directories")
+                .long("maml-directory")
+                .help("Directory containing MAML help files (*-help.xml) to index")
+                .takes_value(true)
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("maml-module-version")
+                .long("maml-module-version")
+                .help("Module version for MAML help files, overriding the version directory")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("psgallery-directories")
                 .short("p")
@@ -29,11 +60,23 @@ fn main() -> anyhow::Result<()> {
                 .takes_value(true)
                 .multiple(true),
         )
+        .arg(
+            Arg::with_name("nupkg-directories")
+                .long("nupkg-directory")
+                .help("Directory containing module packages (*.nupkg) to index")
+                .takes_value(true)
+                .multiple(true),
+        )
         .group(
             ArgGroup::with_name("input-directories")
                 .required(true)
                 .multiple(true)
-                .args(&["psgallery-directories", "markdown-directories"]),
+                .args(&[
+                    "psgallery-directories",
+                    "markdown-directories",
+                    "maml-directories",
+                    "nupkg-directories",
+                ]),
         )
         .arg(
             Arg::with_name("index-directory")
@@ -43,6 +86,11 @@ fn main() -> anyhow::Result<()> {
                 .required(true)
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("incremental")
+                .long("incremental")
+                .help("Update an existing index, only reindexing modules with a new version"),
+        )
         .get_matches();
 
     pretty_env_logger::formatted_timed_builder()
@@ -52,11 +100,24 @@ fn main() -> anyhow::Result<()> {
     let index_directory = matches
         .value_of("index-directory")
         .expect("Index directory is a required parameter");
-    let mut indexer = Indexer::new(index_directory)?;
+    let mut indexer = Indexer::new(index_directory, matches.is_present("incremental"))?;
 
     let markdown_directories = matches.values_of("markdown-directories");
     if let Some(markdown_directories) = markdown_directories {
-        markdown::process_directories(&indexer, markdown_directories)?;
+        let module_name = matches.value_of("markdown-module-name");
+        let module_version = matches.value_of("markdown-module-version");
+        markdown::process_directories(&indexer, markdown_directories, module_name, module_version)?;
+    }
+
+    let maml_directories = matches.values_of("maml-directories");
+    if let Some(maml_directories) = maml_directories {
+        let module_version = matches.value_of("maml-module-version");
+        maml::process_directories(&indexer, maml_directories, module_version)?;
+    }
+
+    let nupkg_directories = matches.values_of("nupkg-directories");
+    if let Some(nupkg_directories) = nupkg_directories {
+        nupkg::process_directories(&indexer, nupkg_directories)?;
     }
 
     let psgallery_directories = matches.values_of("psgallery-directories");
@@ -0,0 +1,500 @@
+use crate::error::FindCmdletError;
+use crate::indexer::Indexer;
+use crate::manifest::ModuleManifest;
+use crate::syntax::{is_common_parameter, ParameterSetSyntax, SyntaxParameter};
+use crate::text::decode_text;
+use find_cmdlet_index::cmdlet::{Cmdlet, Example, Link, Parameter, Source};
+use roxmltree::Node;
+use std::path::Path;
+
+/// First child element with the given name, ignoring namespaces since MAML
+/// files aren't consistent about them
+fn child<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'a str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+/// Concatenate the text within a node
+fn node_text(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Get help text from a MAML description, which is a list of paragraphs
+fn help_text(node: Option<Node>) -> String {
+    let node = match node {
+        Some(node) => node,
+        None => return String::new(),
+    };
+
+    let paras = node
+        .descendants()
+        .filter(|n| n.is_element() && n.tag_name().name() == "para")
+        .map(node_text)
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>();
+    if paras.is_empty() {
+        node_text(node)
+    } else {
+        paras.join("\n")
+    }
+}
+
+fn is_true(node: Node, attribute: &str) -> bool {
+    node.attribute(attribute)
+        .map(|v| v.trim().eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Type of a parameter, from dev:type or the command:parameterValue
+/// placeholder, which is all older help files have
+fn parameter_type(param: Node) -> Option<String> {
+    child(param, "type")
+        .and_then(|t| child(t, "name"))
+        .map(node_text)
+        .filter(|t| !t.is_empty())
+        .or_else(|| {
+            child(param, "parameterValue")
+                .map(node_text)
+                .filter(|t| !t.is_empty())
+        })
+}
+
+/// Build a syntax line from a command:syntaxItem
+fn syntax_item(name: &str, item: Node) -> String {
+    let parameters = children(item, "parameter")
+        .filter_map(|param| {
+            let param_name = child(param, "name").map(node_text)?;
+            if is_common_parameter(&param_name) {
+                return None;
+            }
+
+            // Switches have no value placeholder
+            let type_name = child(param, "parameterValue")
+                .map(node_text)
+                .filter(|t| !t.is_empty());
+            let switch = type_name
+                .as_ref()
+                .map(|t| t.ends_with("SwitchParameter"))
+                .unwrap_or(true);
+
+            Some(SyntaxParameter {
+                name: param_name,
+                type_name,
+                mandatory: is_true(param, "required"),
+                // Named parameters have a position of named
+                position: param.attribute("position").and_then(|p| p.parse().ok()),
+                switch,
+            })
+        })
+        .collect();
+
+    let command = child(item, "name")
+        .map(node_text)
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| name.to_string());
+    ParameterSetSyntax {
+        command,
+        parameters,
+        // Help files never list the common parameters, but nearly every
+        // cmdlet supports them
+        common_parameters: true,
+    }
+    .to_string()
+}
+
+fn build_parameters(command: Node) -> Vec<Parameter> {
+    let mut parameters: Vec<Parameter> = Vec::new();
+
+    let help_parameters = child(command, "parameters")
+        .into_iter()
+        .flat_map(|p| children(p, "parameter"));
+    for param in help_parameters {
+        let name = match child(param, "name").map(node_text) {
+            Some(name) if !name.is_empty() && !is_common_parameter(&name) => name,
+            _ => continue,
+        };
+        let mut parameter = Parameter {
+            name,
+            type_name: parameter_type(param).unwrap_or_default(),
+            mandatory: is_true(param, "required"),
+            description: help_text(child(param, "description")),
+            ..Default::default()
+        };
+        if let Some(aliases) = param.attribute("aliases") {
+            parameter.add_aliases(aliases);
+        }
+        if let Some(pipeline_input) = param.attribute("pipelineInput") {
+            parameter.set_pipeline_input(pipeline_input);
+        }
+
+        parameters.push(parameter);
+    }
+
+    // Some help files only describe parameters in the syntax
+    let syntax_parameters = child(command, "syntax")
+        .into_iter()
+        .flat_map(|s| children(s, "syntaxItem"))
+        .flat_map(|item| children(item, "parameter"));
+    for param in syntax_parameters {
+        let name = match child(param, "name").map(node_text) {
+            Some(name) if !name.is_empty() && !is_common_parameter(&name) => name,
+            _ => continue,
+        };
+        if parameters
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(&name))
+        {
+            continue;
+        }
+
+        parameters.push(Parameter {
+            name,
+            type_name: parameter_type(param).unwrap_or_default(),
+            mandatory: is_true(param, "required"),
+            description: help_text(child(param, "description")),
+            ..Default::default()
+        });
+    }
+
+    parameters
+}
+
+fn build_examples(command: Node) -> Vec<Example> {
+    child(command, "examples")
+        .into_iter()
+        .flat_map(|e| children(e, "example"))
+        .filter_map(|example| {
+            let code = children(example, "code")
+                .map(node_text)
+                .filter(|c| !c.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            // Older help files put the prompt in the introduction
+            let introduction = help_text(child(example, "introduction"));
+            let code = if introduction.ends_with('>') && !code.is_empty() {
+                format!("{} {}", introduction, code)
+            } else {
+                code
+            };
+            let remarks = help_text(child(example, "remarks"));
+            if code.is_empty() && remarks.is_empty() {
+                return None;
+            }
+
+            // Titles are surrounded by dashes, eg. ------ Example 1 ------
+            let title = child(example, "title")
+                .map(node_text)
+                .unwrap_or_default()
+                .trim_matches(|c: char| c == '-' || c.is_whitespace())
+                .to_string();
+
+            Some(Example {
+                title,
+                code,
+                remarks,
+            })
+        })
+        .collect()
+}
+
+fn build_links(command: Node) -> Vec<Link> {
+    child(command, "relatedLinks")
+        .into_iter()
+        .flat_map(|l| children(l, "navigationLink"))
+        .filter_map(|link| {
+            let title = child(link, "linkText").map(node_text).unwrap_or_default();
+            let uri = child(link, "uri").map(node_text).unwrap_or_default();
+            if title.is_empty() && uri.is_empty() {
+                None
+            } else {
+                Some(Link { title, uri })
+            }
+        })
+        .collect()
+}
+
+fn process_command(command: Node, module: &str, module_version: &str) -> anyhow::Result<Cmdlet> {
+    let details = child(command, "details");
+    let name = details
+        .and_then(|d| child(d, "name"))
+        .map(node_text)
+        .filter(|n| !n.is_empty())
+        .ok_or(FindCmdletError::MissingCmdletName)?;
+
+    let syntax = child(command, "syntax")
+        .into_iter()
+        .flat_map(|s| children(s, "syntaxItem"))
+        .map(|item| syntax_item(&name, item))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let links = build_links(command);
+    // PlatyPS writes the online version as the first related link
+    let url = links
+        .iter()
+        .find(|l| l.uri.starts_with("http://") || l.uri.starts_with("https://"))
+        .map(|l| l.uri.clone())
+        .unwrap_or_default();
+
+    Ok(Cmdlet {
+        module: module.to_string(),
+        module_version: module_version.to_string(),
+        synopsis: help_text(details.and_then(|d| child(d, "description"))),
+        syntax,
+        description: help_text(child(command, "description")),
+        notes: help_text(child(command, "alertSet")),
+        parameters: build_parameters(command),
+        examples: build_examples(command),
+        links,
+        url,
+        name,
+        aliases: Vec::new(),
+        tags: Vec::new(),
+        module_info: Default::default(),
+        source: Source::Maml,
+    })
+}
+
+/// Parse the commands in a MAML help file. Commands which can't be parsed are
+/// logged and skipped.
+pub fn process_maml(text: &str, module: &str, module_version: &str) -> anyhow::Result<Vec<Cmdlet>> {
+    let doc = roxmltree::Document::parse(text.trim_start_matches('\u{feff}'))?;
+
+    Ok(children(doc.root_element(), "command")
+        .filter_map(
+            |command| match process_command(command, module, module_version) {
+                Ok(cmdlet) => Some(cmdlet),
+                Err(e) => {
+                    log::warn!("{:?}", e);
+                    None
+                }
+            },
+        )
+        .collect())
+}
+
+/// Culture directory help is installed in, eg. en-US
+fn is_culture(name: &str) -> bool {
+    let mut parts = name.split('-');
+    let language = parts.next().unwrap_or("");
+    language.len() == 2
+        && language.chars().all(|c| c.is_ascii_lowercase())
+        && parts.all(|p| (2..=4).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+fn is_version(name: &str) -> bool {
+    name.split('.')
+        .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Work out the module a help file belongs to from the usual module layout,
+/// <Module>/<Version>/<culture>/<Assembly>-help.xml, where the version and
+/// culture directories are optional. Without a version directory, the version
+/// comes from the module's manifest, <Module>/<Module>.psd1.
+fn module_for_path(
+    path: &Path,
+    version_override: Option<&str>,
+) -> anyhow::Result<(String, String)> {
+    let mut dirs = path
+        .ancestors()
+        .skip(1)
+        .filter_map(|d| Some((d, d.file_name()?.to_string_lossy().to_string())));
+
+    let mut dir = dirs.next();
+    if dir.as_ref().map(|(_, n)| is_culture(n)).unwrap_or(false) {
+        dir = dirs.next();
+    }
+    let mut version = None;
+    if dir.as_ref().map(|(_, n)| is_version(n)).unwrap_or(false) {
+        version = dir.map(|(_, n)| n);
+        dir = dirs.next();
+    }
+
+    let (module_dir, name) = dir.ok_or(FindCmdletError::MissingModuleName)?;
+    let version = version_override
+        .map(|v| v.to_string())
+        .or(version)
+        .or_else(|| {
+            ModuleManifest::read(&module_dir.join(format!("{}.psd1", name)))
+                .ok()
+                .map(|manifest| manifest.module_version)
+                .filter(|v| !v.is_empty())
+        })
+        .ok_or(FindCmdletError::MissingModuleVersion)?;
+
+    Ok((name, version))
+}
+
+fn process_file_maml(path: &Path, version_override: Option<&str>) -> anyhow::Result<Vec<Cmdlet>> {
+    let (module, module_version) = module_for_path(path, version_override)?;
+
+    let text = decode_text(&std::fs::read(path)?);
+
+    process_maml(&text, &module, &module_version)
+}
+
+fn is_maml_file(e: &walkdir::DirEntry) -> bool {
+    e.file_type().is_file()
+        && e.file_name()
+            .to_string_lossy()
+            .to_ascii_lowercase()
+            .ends_with("-help.xml")
+}
+
+pub fn process_directories<I>(
+    indexer: &Indexer,
+    directories: I,
+    module_version: Option<&str>,
+) -> anyhow::Result<()>
+where
+    I: IntoIterator,
+    I::Item: AsRef<Path>,
+{
+    let dir_walker = directories.into_iter().flat_map(|d| {
+        walkdir::WalkDir::new(d)
+            .into_iter()
+            .filter_entry(|e| e.file_type().is_dir() || is_maml_file(e))
+    });
+
+    for dir_entry in dir_walker {
+        let de = match dir_entry {
+            Ok(de) => de,
+            Err(e) => {
+                log::warn!("{:?}", e);
+                continue;
+            }
+        };
+        if de.file_type().is_dir() {
+            continue;
+        }
+
+        let cmdlets = match process_file_maml(de.path(), module_version) {
+            Ok(cmdlets) => cmdlets,
+            Err(e) => {
+                log::warn!("{}: {:?}", de.path().display(), e);
+                continue;
+            }
+        };
+        for cmdlet in cmdlets {
+            match indexer.replace_module(&cmdlet.module, &cmdlet.module_version) {
+                Ok(true) => indexer.update(&cmdlet),
+                Ok(false) => {}
+                Err(e) => log::warn!("{:?}", e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn maml_command() {
+    let maml = r#"<?xml version="1.0" encoding="utf-8"?>
+<helpItems schema="maml" xmlns="http://msh">
+  <command:command xmlns:maml="http://schemas.microsoft.com/maml/2004/10" xmlns:command="http://schemas.microsoft.com/maml/dev/command/2004/10" xmlns:dev="http://schemas.microsoft.com/maml/dev/2004/10">
+    <command:details>
+      <command:name>Get-Thing</command:name>
+      <maml:description><maml:para>Gets things.</maml:para></maml:description>
+    </command:details>
+    <maml:description>
+      <maml:para>The Get-Thing cmdlet gets things.</maml:para>
+      <maml:para>It gets them quickly.</maml:para>
+    </maml:description>
+    <command:syntax>
+      <command:syntaxItem>
+        <maml:name>Get-Thing</maml:name>
+        <command:parameter required="true" position="0" aliases="n">
+          <maml:name>Name</maml:name>
+          <command:parameterValue required="true">String[]</command:parameterValue>
+        </command:parameter>
+        <command:parameter required="false" position="named">
+          <maml:name>Force</maml:name>
+        </command:parameter>
+      </command:syntaxItem>
+    </command:syntax>
+    <command:parameters>
+      <command:parameter required="true" pipelineInput="True (ByValue)" position="0" aliases="n">
+        <maml:name>Name</maml:name>
+        <maml:description><maml:para>Names of the things to get.</maml:para></maml:description>
+        <command:parameterValue required="true">String[]</command:parameterValue>
+        <dev:type><maml:name>System.String[]</maml:name></dev:type>
+      </command:parameter>
+    </command:parameters>
+    <maml:alertSet><maml:alert><maml:para>Things may vary.</maml:para></maml:alert></maml:alertSet>
+    <command:examples>
+      <command:example>
+        <maml:title>-------------------------- Example 1: Get a thing --------------------------</maml:title>
+        <dev:code>Get-Thing -Name one</dev:code>
+        <dev:remarks><maml:para>Gets the thing called one.</maml:para></dev:remarks>
+      </command:example>
+    </command:examples>
+    <command:relatedLinks>
+      <maml:navigationLink>
+        <maml:linkText>Online Version:</maml:linkText>
+        <maml:uri>https://example.com/get-thing</maml:uri>
+      </maml:navigationLink>
+      <maml:navigationLink>
+        <maml:linkText>Set-Thing</maml:linkText>
+        <maml:uri></maml:uri>
+      </maml:navigationLink>
+    </command:relatedLinks>
+  </command:command>
+</helpItems>"#;
+
+    let cmdlets = process_maml(maml, "Things", "1.0.0").unwrap();
+    assert_eq!(cmdlets.len(), 1);
+    let cmdlet = &cmdlets[0];
+    assert_eq!(cmdlet.name, "Get-Thing");
+    assert_eq!(cmdlet.synopsis, "Gets things.");
+    assert_eq!(
+        cmdlet.description,
+        "The Get-Thing cmdlet gets things.\nIt gets them quickly."
+    );
+    assert_eq!(
+        cmdlet.syntax,
+        "Get-Thing [-Name] <string[]> [-Force] [<CommonParameters>]"
+    );
+    assert_eq!(cmdlet.url, "https://example.com/get-thing");
+    assert_eq!(cmdlet.notes, "Things may vary.");
+    assert_eq!(cmdlet.links.len(), 2);
+    assert_eq!(cmdlet.parameters.len(), 2);
+    let name = &cmdlet.parameters[0];
+    assert_eq!(name.type_name, "System.String[]");
+    assert_eq!(name.aliases, vec!["n"]);
+    assert!(name.mandatory && name.pipeline_by_value);
+    assert_eq!(cmdlet.examples[0].title, "Example 1: Get a thing");
+    assert_eq!(cmdlet.examples[0].code, "Get-Thing -Name one");
+
+    assert_eq!(
+        module_for_path(Path::new("/m/Things/1.0.0/en-US/Things.dll-help.xml"), None).unwrap(),
+        ("Things".to_string(), "1.0.0".to_string())
+    );
+    assert!(module_for_path(Path::new("/m/Things/en-US/Things.dll-help.xml"), None).is_err());
+
+    let directory = std::env::temp_dir().join(format!("find-cmdlet-maml-{}", std::process::id()));
+    let module_dir = directory.join("Things");
+    std::fs::create_dir_all(&module_dir).unwrap();
+    std::fs::write(
+        module_dir.join("Things.psd1"),
+        "@{ ModuleVersion = '1.1.0' }",
+    )
+    .unwrap();
+    let resolved = module_for_path(&module_dir.join("en-US").join("Things.dll-help.xml"), None);
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(
+        resolved.unwrap(),
+        ("Things".to_string(), "1.1.0".to_string())
+    );
+}
@@ -0,0 +1,260 @@
+use crate::psd1::{self, Psd1Error, Psd1Value};
+use crate::text::decode_text;
+use find_cmdlet_index::cmdlet::ModuleInfo;
+use std::path::Path;
+
+/// The parts of a module manifest (.psd1) the index uses
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModuleManifest {
+    /// ModuleVersion, empty if the manifest doesn't have one
+    pub module_version: String,
+
+    pub cmdlets_to_export: Vec<String>,
+    pub functions_to_export: Vec<String>,
+
+    /// None when the manifest doesn't list aliases, so exports whatever the
+    /// module defines
+    pub aliases_to_export: Option<Vec<String>>,
+
+    /// Tags from PrivateData.PSData, which the gallery shows
+    pub tags: Vec<String>,
+
+    pub info: ModuleInfo,
+}
+
+fn string(value: Option<&Psd1Value>) -> String {
+    value
+        .and_then(Psd1Value::as_str)
+        .unwrap_or("")
+        .trim()
+        .to_string()
+}
+
+fn strings(value: Option<&Psd1Value>) -> Vec<String> {
+    value
+        .map(Psd1Value::strings)
+        .unwrap_or_default()
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+/// Required modules are either names or hashtables, eg.
+/// @{ ModuleName = 'Az.Accounts'; ModuleVersion = '2.2.0' }
+fn required_modules(value: Option<&Psd1Value>) -> Vec<String> {
+    let modules = match value {
+        Some(Psd1Value::Array(modules)) => modules.iter().collect(),
+        Some(module) => vec![module],
+        None => vec![],
+    };
+
+    modules
+        .into_iter()
+        .filter_map(|module| match module {
+            Psd1Value::Hashtable(_) => module.get("ModuleName").and_then(Psd1Value::as_str),
+            _ => module.as_str(),
+        })
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+pub fn is_wildcard(pattern: &str) -> bool {
+    pattern.contains('*') || pattern.contains('?')
+}
+
+/// Whether a name matches a wildcard pattern from a manifest or
+/// Export-ModuleMember, eg. Get-*, ignoring case. Only * and ? are supported.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let name = name.to_lowercase().chars().collect::<Vec<_>>();
+
+    let mut p = 0;
+    let mut n = 0;
+    // Where to backtrack to, letting the last * match one more character
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+impl ModuleManifest {
+    pub fn parse(text: &str) -> Result<ModuleManifest, Psd1Error> {
+        let manifest = psd1::parse(text)?;
+        let ps_data = manifest.get("PrivateData").and_then(|p| p.get("PSData"));
+
+        Ok(ModuleManifest {
+            module_version: string(manifest.get("ModuleVersion")),
+            cmdlets_to_export: strings(manifest.get("CmdletsToExport")),
+            functions_to_export: strings(manifest.get("FunctionsToExport")),
+            aliases_to_export: manifest
+                .get("AliasesToExport")
+                .map(|aliases| strings(Some(aliases))),
+            tags: strings(ps_data.and_then(|p| p.get("Tags"))),
+            info: ModuleInfo {
+                author: string(manifest.get("Author")),
+                description: string(manifest.get("Description")),
+                project_uri: string(ps_data.and_then(|p| p.get("ProjectUri"))),
+                published: String::new(),
+                guid: string(manifest.get("GUID")),
+                company_name: string(manifest.get("CompanyName")),
+                license_uri: string(ps_data.and_then(|p| p.get("LicenseUri"))),
+                powershell_version: string(manifest.get("PowerShellVersion")),
+                compatible_editions: strings(manifest.get("CompatiblePSEditions")),
+                required_modules: required_modules(manifest.get("RequiredModules")),
+            },
+        })
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<ModuleManifest> {
+        let text = decode_text(&std::fs::read(path)?);
+
+        Ok(ModuleManifest::parse(&text)?)
+    }
+
+    /// Commands the manifest exports by name. Wildcards, which export
+    /// whatever the module defines that matches, are skipped.
+    pub fn exported_commands(&self) -> Vec<String> {
+        self.cmdlets_to_export
+            .iter()
+            .chain(&self.functions_to_export)
+            .filter(|c| !is_wildcard(c))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether the manifest exports an alias the module's scripts define
+    pub fn exports_alias(&self, alias: &str) -> bool {
+        match &self.aliases_to_export {
+            Some(aliases) => aliases.iter().any(|a| wildcard_match(a, alias)),
+            None => true,
+        }
+    }
+
+    /// Fill in module information that other sources didn't have
+    pub fn fill_info(&self, info: &mut ModuleInfo) {
+        let fill = |field: &mut String, value: &String| {
+            if field.is_empty() {
+                *field = value.clone();
+            }
+        };
+        fill(&mut info.author, &self.info.author);
+        fill(&mut info.description, &self.info.description);
+        fill(&mut info.project_uri, &self.info.project_uri);
+        fill(&mut info.guid, &self.info.guid);
+        fill(&mut info.company_name, &self.info.company_name);
+        fill(&mut info.license_uri, &self.info.license_uri);
+        fill(&mut info.powershell_version, &self.info.powershell_version);
+        if info.compatible_editions.is_empty() {
+            info.compatible_editions = self.info.compatible_editions.clone();
+        }
+        if info.required_modules.is_empty() {
+            info.required_modules = self.info.required_modules.clone();
+        }
+    }
+
+    /// Whether ModuleVersion is the same as a version from elsewhere, eg. a
+    /// nuspec, which may have dropped trailing zeros or added a prerelease
+    /// suffix
+    pub fn matches_version(&self, version: &str) -> bool {
+        let parts = |version: &str| {
+            let release = version.split('-').next().unwrap_or("");
+            let mut parts = release.trim().split('.').collect::<Vec<_>>();
+            while parts.len() > 1 && parts.last() == Some(&"0") {
+                parts.pop();
+            }
+            parts.join(".")
+        };
+
+        self.module_version.is_empty() || parts(&self.module_version) == parts(version)
+    }
+
+    /// Add the manifest's tags to a list of tags, skipping duplicates
+    pub fn merge_tags(&self, tags: &mut Vec<String>) {
+        for tag in &self.tags {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.clone());
+            }
+        }
+    }
+}
+
+#[test]
+fn module_manifest() {
+    let manifest = ModuleManifest::parse(
+        r#"
+        @{
+            ModuleVersion = '1.2.0.0'
+            GUID = 'd0a9150d-b6a4-4b17-a325-e3a24fed0aa9'
+            Author = 'Someone'
+            CompanyName = 'Things Inc'
+            Description = 'Manages things'
+            PowerShellVersion = '5.1'
+            CompatiblePSEditions = 'Desktop', 'Core'
+            RequiredModules = @(
+                'Stuff',
+                @{ ModuleName = 'Az.Accounts'; ModuleVersion = '2.2.0' }
+            )
+            CmdletsToExport = '*'
+            FunctionsToExport = @('Get-Thing', 'Set-Thing')
+            AliasesToExport = 'gth'
+            PrivateData = @{
+                PSData = @{
+                    Tags = 'things', 'stuff'
+                    LicenseUri = 'https://example.com/license'
+                    ProjectUri = 'https://example.com/things'
+                }
+            }
+        }
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(manifest.exported_commands(), vec!["Get-Thing", "Set-Thing"]);
+    assert_eq!(manifest.module_version, "1.2.0.0");
+    assert!(manifest.matches_version("1.2.0-beta1"));
+    assert!(!manifest.matches_version("1.2.1"));
+    assert!(manifest.exports_alias("GTH"));
+    assert!(!manifest.exports_alias("sth"));
+    assert!(wildcard_match("get-*", "Get-Thing"));
+    assert!(wildcard_match("*-Th?ng", "Get-Thing"));
+    assert!(!wildcard_match("Get-*", "Set-Thing"));
+    assert!(!wildcard_match("Get", "Get-Thing"));
+    assert_eq!(manifest.info.company_name, "Things Inc");
+    assert_eq!(manifest.info.powershell_version, "5.1");
+    assert_eq!(manifest.info.compatible_editions, vec!["Desktop", "Core"]);
+    assert_eq!(manifest.info.required_modules, vec!["Stuff", "Az.Accounts"]);
+    assert_eq!(manifest.info.license_uri, "https://example.com/license");
+
+    let mut info = ModuleInfo {
+        author: "Someone Else".to_string(),
+        ..Default::default()
+    };
+    manifest.fill_info(&mut info);
+    assert_eq!(info.author, "Someone Else");
+    assert_eq!(info.guid, "d0a9150d-b6a4-4b17-a325-e3a24fed0aa9");
+
+    let mut tags = vec!["Things".to_string()];
+    manifest.merge_tags(&mut tags);
+    assert_eq!(tags, vec!["Things", "stuff"]);
+}
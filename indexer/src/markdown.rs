@@ -1,7 +1,9 @@
-use crate::cmdlet::Cmdlet;
 use crate::error::FindCmdletError;
 use crate::indexer::Indexer;
-use path::Path;
+use crate::syntax::is_common_parameter;
+use find_cmdlet_index::cmdlet::{Cmdlet, Example, Parameter, Source};
+use path::{Path, PathBuf};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path;
@@ -13,13 +15,21 @@ enum MDState {
     StoreText,
 }
 
-fn process_file_md<P: AsRef<path::Path>>(path: P) -> anyhow::Result<Cmdlet> {
-    let arena = comrak::Arena::new();
+/// Read a markdown file, splitting off the yaml front matter that comrak
+/// doesn't support
+fn read_md<P: AsRef<path::Path>>(path: P) -> anyhow::Result<(String, yaml_rust::Yaml, usize)> {
     let file = File::open(path)?;
     let mut buf_reader = BufReader::new(file);
     let mut text = String::new();
     buf_reader.read_to_string(&mut text)?;
 
+    let (metadata, markdown_start_idx) = split_md(&text)?;
+    Ok((text, metadata, markdown_start_idx))
+}
+
+/// Parse the yaml front matter of markdown text, returning it and where the
+/// markdown starts
+fn split_md(text: &str) -> anyhow::Result<(yaml_rust::Yaml, usize)> {
     // comrak doesn't support yaml metadata, so hack around it...
     let matches = text.match_indices("---");
     let mut found = 0;
@@ -48,18 +58,189 @@ fn process_file_md<P: AsRef<path::Path>>(path: P) -> anyhow::Result<Cmdlet> {
         return Err(FindCmdletError::MissingMetaDataEnd.into());
     }
 
-    let metadata = yaml_rust::YamlLoader::load_from_str(&text[yaml_start_idx..yaml_end_idx])?;
+    let mut metadata = yaml_rust::YamlLoader::load_from_str(&text[yaml_start_idx..yaml_end_idx])?;
     if metadata.len() != 1 || metadata[0].as_hash().is_none() {
         return Err(FindCmdletError::UnexpectedMetaData.into());
     }
-    let metadata = &metadata[0];
-    //println!("{:?}", metadata);
+    let metadata = metadata.remove(0);
+
+    Ok((metadata, markdown_start_idx))
+}
+
+/// Get a scalar metadata value as a string, since yaml will happily turn a
+/// version like 1.0 into a float
+fn yaml_str(val: &yaml_rust::Yaml) -> Option<String> {
+    match val {
+        yaml_rust::Yaml::String(s) | yaml_rust::Yaml::Real(s) if !s.trim().is_empty() => {
+            Some(s.trim().to_string())
+        }
+        yaml_rust::Yaml::Integer(i) => Some(i.to_string()),
+        _ => None,
+    }
+}
+
+/// PlatyPS module pages have a Module Guid rather than a cmdlet title
+fn is_module_page(metadata: &yaml_rust::Yaml) -> bool {
+    metadata["title"].is_badvalue() && !metadata["Module Guid"].is_badvalue()
+}
+
+/// Version for markdown help when neither a module page nor
+/// --markdown-module-version gives one
+const PLACEHOLDER_VERSION: &str = "0.0.0";
+
+#[derive(Clone)]
+struct MDModule {
+    name: String,
+    version: Option<String>,
+}
+
+/// Resolves the module for cmdlet pages, caching the module pages that have
+/// been read
+struct MDModuleResolver<'a> {
+    name_override: Option<&'a str>,
+    version_override: Option<&'a str>,
+    module_pages: HashMap<PathBuf, Option<MDModule>>,
+    module_dirs: HashMap<PathBuf, Option<MDModule>>,
+}
+
+impl<'a> MDModuleResolver<'a> {
+    fn new(name_override: Option<&'a str>, version_override: Option<&'a str>) -> Self {
+        MDModuleResolver {
+            name_override,
+            version_override,
+            module_pages: HashMap::new(),
+            module_dirs: HashMap::new(),
+        }
+    }
+
+    fn read_module_page(path: &Path) -> Option<MDModule> {
+        let (_, metadata, _) = read_md(path).ok()?;
+        if !is_module_page(&metadata) {
+            return None;
+        }
+
+        Some(MDModule {
+            name: yaml_str(&metadata["Module Name"])?,
+            version: yaml_str(&metadata["Help Version"]),
+        })
+    }
 
-    let doc = comrak::parse_document(
-        &arena,
+    /// Find the module page for a module, <Module>.md next to the cmdlet page
+    fn module_page(&mut self, dir: &Path, module_name: &str) -> Option<MDModule> {
+        let path = dir.join(format!("{}.md", module_name));
+        if !self.module_pages.contains_key(&path) {
+            let module = Self::read_module_page(&path);
+            self.module_pages.insert(path.clone(), module);
+        }
+
+        self.module_pages[&path].clone()
+    }
+
+    /// Find any module page in a directory
+    fn module_dir(&mut self, dir: &Path) -> Option<MDModule> {
+        if !self.module_dirs.contains_key(dir) {
+            let module = std::fs::read_dir(dir).ok().and_then(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter(|de| de.file_name().to_string_lossy().ends_with(".md"))
+                    .find_map(|de| Self::read_module_page(&de.path()))
+            });
+            self.module_dirs.insert(dir.to_path_buf(), module);
+        }
+
+        self.module_dirs[dir].clone()
+    }
+
+    fn resolve(
+        &mut self,
+        path: &Path,
+        metadata: &yaml_rust::Yaml,
+    ) -> anyhow::Result<(String, String)> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let module = match yaml_str(&metadata["Module Name"]) {
+            Some(name) => self.module_page(dir, &name).unwrap_or(MDModule {
+                name,
+                version: None,
+            }),
+            // Without a module page either, use the override or assume the
+            // help is in a directory named after the module
+            None => match self.module_dir(dir) {
+                Some(module) => module,
+                None => MDModule {
+                    name: self
+                        .name_override
+                        .map(|n| n.to_string())
+                        .or_else(|| dir.file_name().map(|n| n.to_string_lossy().to_string()))
+                        .ok_or(FindCmdletError::MissingModuleName)?,
+                    version: None,
+                },
+            },
+        };
+
+        let name = self
+            .name_override
+            .map(|n| n.to_string())
+            .unwrap_or(module.name);
+        let version = self
+            .version_override
+            .map(|v| v.to_string())
+            .or(module.version)
+            .unwrap_or_else(|| PLACEHOLDER_VERSION.to_string());
+
+        Ok((name, version))
+    }
+}
+
+fn process_file_md<P: AsRef<path::Path>>(
+    path: P,
+    modules: &mut MDModuleResolver,
+) -> anyhow::Result<Option<Cmdlet>> {
+    let (text, metadata, markdown_start_idx) = read_md(&path)?;
+    if is_module_page(&metadata) {
+        return Ok(None);
+    }
+    let (module, module_version) = modules.resolve(path.as_ref(), &metadata)?;
+
+    build_cmdlet_md(
         &text[markdown_start_idx..],
-        &comrak::ComrakOptions::default(),
-    );
+        &metadata,
+        module,
+        module_version,
+    )
+    .map(Some)
+}
+
+/// Parse markdown help text for a cmdlet in a known module, eg. from a
+/// package. Returns None for module pages.
+pub fn process_markdown(
+    text: &str,
+    module: &str,
+    module_version: &str,
+) -> anyhow::Result<Option<Cmdlet>> {
+    let (metadata, markdown_start_idx) = split_md(text)?;
+    if is_module_page(&metadata) {
+        return Ok(None);
+    }
+
+    build_cmdlet_md(
+        &text[markdown_start_idx..],
+        &metadata,
+        module.to_string(),
+        module_version.to_string(),
+    )
+    .map(Some)
+}
+
+fn build_cmdlet_md(
+    markdown: &str,
+    metadata: &yaml_rust::Yaml,
+    module: String,
+    module_version: String,
+) -> anyhow::Result<Cmdlet> {
+    let arena = comrak::Arena::new();
+    //println!("{:?}", metadata);
+
+    let doc = comrak::parse_document(&arena, markdown, &comrak::ComrakOptions::default());
 
     //println!("{:?}", doc);
     let mut sections = Vec::new();
@@ -126,20 +307,162 @@ fn process_file_md<P: AsRef<path::Path>>(path: P) -> anyhow::Result<Cmdlet> {
         }
     }
 
+    let parameters = process_parameters_md(doc);
+    let examples = process_examples_md(doc);
+
     Ok(Cmdlet {
-        module: "TODO".to_string(),
-        module_version: "TODO".to_string(),
+        module,
+        module_version,
         name,
+        aliases: Vec::new(),
         url,
         tags,
         synopsis,
         syntax,
         description,
         notes,
+        parameters,
+        examples,
+        links: Vec::new(),
+        module_info: Default::default(),
+        source: Source::Markdown,
     })
 }
 
-pub fn process_directories<I>(indexer: &Indexer, directories: I) -> anyhow::Result<()>
+/// Update a parameter from the metadata block PlatyPS writes after each
+/// parameter's description, eg.
+///
+/// ```yaml
+/// Type: String[]
+/// Parameter Sets: (All)
+/// Aliases: cn
+/// Required: True
+/// Accept pipeline input: True (ByPropertyName, ByValue)
+/// ```
+fn update_parameter_md(parameter: &mut Parameter, metadata: &str) {
+    for line in metadata.lines() {
+        let mut kv = line.splitn(2, ':');
+        let key = kv.next().unwrap_or("").trim().to_ascii_lowercase();
+        let value = kv.next().unwrap_or("").trim();
+        match key.as_str() {
+            "type" if parameter.type_name.is_empty() => parameter.type_name = value.to_string(),
+            "parameter sets" => {
+                for set in value.split(',').map(str::trim) {
+                    if !set.is_empty()
+                        && set != "(All)"
+                        && !parameter.parameter_sets.iter().any(|s| s == set)
+                    {
+                        parameter.parameter_sets.push(set.to_string());
+                    }
+                }
+            }
+            "aliases" => parameter.add_aliases(value),
+            "required" => parameter.mandatory |= value.to_ascii_lowercase().contains("true"),
+            "accept pipeline input" => parameter.set_pipeline_input(value),
+            _ => {}
+        }
+    }
+}
+
+/// A level 3 heading and its content, eg. a single parameter or example
+struct MDSubsection {
+    heading: String,
+    paragraphs: Vec<String>,
+    code_blocks: Vec<String>,
+}
+
+/// Collect the level 3 subsections within a level 2 section
+fn subsections_md<'a>(doc: &'a comrak::nodes::AstNode<'a>, section: &str) -> Vec<MDSubsection> {
+    let mut subsections = Vec::new();
+    let mut in_section = false;
+    let mut current: Option<MDSubsection> = None;
+    iter_nodes(doc, &mut |node| match node.data.borrow().value {
+        comrak::nodes::NodeValue::Heading(comrak::nodes::NodeHeading { level, .. }) => {
+            if level <= 3 {
+                subsections.extend(current.take());
+            }
+            if level == 2 {
+                in_section = node_text(node).trim().eq_ignore_ascii_case(section);
+            } else if level == 3 && in_section {
+                current = Some(MDSubsection {
+                    heading: node_text(node).trim().to_string(),
+                    paragraphs: Vec::new(),
+                    code_blocks: Vec::new(),
+                });
+            }
+        }
+        comrak::nodes::NodeValue::Paragraph => {
+            if let Some(subsection) = current.as_mut() {
+                subsection
+                    .paragraphs
+                    .push(node_text(node).trim().to_string());
+            }
+        }
+        comrak::nodes::NodeValue::CodeBlock(ref code_block) => {
+            if let Some(subsection) = current.as_mut() {
+                let code = String::from_utf8_lossy(&code_block.literal);
+                subsection.code_blocks.push(code.trim_end().to_string());
+            }
+        }
+        _ => {}
+    });
+    subsections.extend(current);
+
+    subsections
+}
+
+fn process_parameters_md<'a>(doc: &'a comrak::nodes::AstNode<'a>) -> Vec<Parameter> {
+    subsections_md(doc, "PARAMETERS")
+        .into_iter()
+        .filter_map(|subsection| {
+            let name = subsection.heading.trim_start_matches('-');
+            if is_common_parameter(name) || name.eq_ignore_ascii_case("CommonParameters") {
+                return None;
+            }
+
+            let mut parameter = Parameter {
+                name: name.to_string(),
+                description: subsection.paragraphs.join("\n"),
+                ..Default::default()
+            };
+            for metadata in &subsection.code_blocks {
+                update_parameter_md(&mut parameter, metadata);
+            }
+
+            Some(parameter)
+        })
+        .collect()
+}
+
+fn process_examples_md<'a>(doc: &'a comrak::nodes::AstNode<'a>) -> Vec<Example> {
+    subsections_md(doc, "EXAMPLES")
+        .into_iter()
+        .map(|subsection| Example {
+            title: subsection.heading,
+            code: subsection.code_blocks.join("\n\n"),
+            remarks: subsection.paragraphs.join("\n"),
+        })
+        .collect()
+}
+
+/// Concatenate the text within a node
+fn node_text<'a>(node: &'a comrak::nodes::AstNode<'a>) -> String {
+    let mut text = String::new();
+    iter_nodes(node, &mut |node| match node.data.borrow().value {
+        comrak::nodes::NodeValue::Text(ref t) => text += &String::from_utf8_lossy(t),
+        comrak::nodes::NodeValue::SoftBreak | comrak::nodes::NodeValue::LineBreak => text += " ",
+        _ => {}
+    });
+
+    text
+}
+
+pub fn process_directories<I>(
+    indexer: &Indexer,
+    directories: I,
+    module_name: Option<&str>,
+    module_version: Option<&str>,
+) -> anyhow::Result<()>
 where
     I: IntoIterator,
     I::Item: AsRef<Path>,
@@ -154,14 +477,22 @@ where
         })
         .flatten();
 
+    let mut modules = MDModuleResolver::new(module_name, module_version);
     for dir_entry in dir_walker.into_iter() {
         match dir_entry {
             Ok(de) => {
                 if de.file_type().is_dir() {
                     continue;
                 }
-                match process_file_md(de.path()) {
-                    Ok(cmdlet) => indexer.update(&cmdlet),
+                match process_file_md(de.path(), &mut modules) {
+                    Ok(Some(cmdlet)) => {
+                        match indexer.replace_module(&cmdlet.module, &cmdlet.module_version) {
+                            Ok(true) => indexer.update(&cmdlet),
+                            Ok(false) => {}
+                            Err(e) => log::warn!("{:?}", e),
+                        }
+                    }
+                    Ok(None) => {}
                     Err(e) => log::warn!("{:?}", e),
                 }
             }
@@ -181,3 +512,49 @@ where
         iter_nodes(c, f);
     }
 }
+
+#[test]
+fn resolve_module() {
+    let directory =
+        std::env::temp_dir().join(format!("find-cmdlet-markdown-{}", std::process::id()));
+    let things = directory.join("Things");
+    let other = directory.join("Other");
+    std::fs::create_dir_all(&things).unwrap();
+    std::fs::create_dir_all(&other).unwrap();
+    std::fs::write(
+        things.join("Things.md"),
+        "---\nModule Name: Things\nModule Guid: d0a9150d-b6a4-4b17-a325-e3a24fed0aa9\nHelp Version: 1.2.0\n---\n",
+    )
+    .unwrap();
+    let metadata = |yaml: &str| {
+        yaml_rust::YamlLoader::load_from_str(yaml)
+            .unwrap()
+            .remove(0)
+    };
+    let cmdlet_page = metadata("title: Get-Thing");
+    let with_module = metadata("title: Get-Thing\nModule Name: Things");
+
+    let mut modules = MDModuleResolver::new(None, None);
+    let resolved = (
+        modules
+            .resolve(&things.join("Get-Thing.md"), &with_module)
+            .unwrap(),
+        modules
+            .resolve(&things.join("Get-Thing.md"), &cmdlet_page)
+            .unwrap(),
+        modules
+            .resolve(&other.join("Get-Other.md"), &cmdlet_page)
+            .unwrap(),
+    );
+    let mut overridden = MDModuleResolver::new(Some("Stuff"), Some("2.0.0"));
+    let override_resolved = overridden
+        .resolve(&other.join("Get-Other.md"), &cmdlet_page)
+        .unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let module = |name: &str, version: &str| (name.to_string(), version.to_string());
+    assert_eq!(resolved.0, module("Things", "1.2.0"));
+    assert_eq!(resolved.1, module("Things", "1.2.0"));
+    assert_eq!(resolved.2, module("Other", PLACEHOLDER_VERSION));
+    assert_eq!(override_resolved, module("Stuff", "2.0.0"));
+}
@@ -0,0 +1,440 @@
+use crate::error::FindCmdletError;
+use crate::indexer::Indexer;
+use crate::manifest::ModuleManifest;
+use crate::text::decode_text;
+use crate::{maml, markdown, script};
+use find_cmdlet_index::cmdlet::{Cmdlet, ModuleInfo, Source};
+use roxmltree::Node;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+/// Tags the gallery generates to list what a package contains, eg.
+/// PSCmdlet_Get-Thing, rather than ones the author chose
+const CONTENT_TAG_PREFIXES: &[&str] = &[
+    "PSCmdlet_",
+    "PSFunction_",
+    "PSCommand_",
+    "PSIncludes_",
+    "PSDscResource_",
+    "PSRoleCapability_",
+    "PSWorkflow_",
+];
+
+/// Package metadata from a .nuspec
+#[derive(Debug, Default)]
+struct Nuspec {
+    id: String,
+    version: String,
+    authors: String,
+    description: String,
+    project_url: String,
+    tags: Vec<String>,
+}
+
+fn parse_nuspec(text: &str) -> anyhow::Result<Nuspec> {
+    let doc = roxmltree::Document::parse(text.trim_start_matches('\u{feff}'))?;
+    let metadata = doc
+        .root_element()
+        .children()
+        .find(|n| n.is_element() && n.tag_name().name() == "metadata")
+        .ok_or(FindCmdletError::MissingModuleName)?;
+    // Namespaces vary with the version of NuGet that packed the module
+    let text = |name: &str| {
+        metadata
+            .children()
+            .find(|n: &Node| n.is_element() && n.tag_name().name() == name)
+            .and_then(|n| n.text())
+            .unwrap_or("")
+            .trim()
+            .to_string()
+    };
+
+    let id = text("id");
+    if id.is_empty() {
+        return Err(FindCmdletError::MissingModuleName.into());
+    }
+    let version = text("version");
+    if version.is_empty() {
+        return Err(FindCmdletError::MissingModuleVersion.into());
+    }
+
+    Ok(Nuspec {
+        id,
+        version,
+        authors: text("authors"),
+        description: text("description"),
+        project_url: text("projectUrl"),
+        tags: text("tags").split_whitespace().map(String::from).collect(),
+    })
+}
+
+/// Add a cmdlet unless one with the same name was found in other help
+fn add_cmdlet(cmdlets: &mut Vec<Cmdlet>, cmdlet: Cmdlet) {
+    if !cmdlets
+        .iter()
+        .any(|c| c.name.eq_ignore_ascii_case(&cmdlet.name))
+    {
+        cmdlets.push(cmdlet);
+    }
+}
+
+/// A .nupkg file, which is a zip archive containing the module
+struct Package<R> {
+    archive: zip::ZipArchive<R>,
+    files: Vec<String>,
+    nuspec: Nuspec,
+}
+
+impl<R: Read + Seek> Package<R> {
+    fn open(reader: R) -> anyhow::Result<Self> {
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let files = (0..archive.len())
+            .filter_map(|i| archive.by_index(i).ok().map(|f| f.name().to_string()))
+            .filter(|name| !name.ends_with('/'))
+            .collect::<Vec<_>>();
+
+        // The .nuspec is the only one in the root of the package
+        let nuspec_file = files
+            .iter()
+            .find(|f| !f.contains('/') && f.to_ascii_lowercase().ends_with(".nuspec"))
+            .ok_or_else(|| FindCmdletError::MissingNuspec(files.join(", ")))?
+            .clone();
+
+        let mut package = Package {
+            archive,
+            files,
+            nuspec: Nuspec::default(),
+        };
+        package.nuspec = parse_nuspec(&package.read(&nuspec_file)?)?;
+
+        Ok(package)
+    }
+
+    fn read(&mut self, name: &str) -> anyhow::Result<String> {
+        let mut file = self.archive.by_name(name)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        Ok(decode_text(&bytes))
+    }
+
+    /// Files in the package with names ending in the given suffix
+    fn files_ending_with(&self, suffix: &str) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|f| f.to_ascii_lowercase().ends_with(suffix))
+            .cloned()
+            .collect()
+    }
+
+    /// The module manifest, <id>.psd1 in the root of the package
+    fn manifest(&mut self) -> Option<ModuleManifest> {
+        let manifest_file = format!("{}.psd1", self.nuspec.id);
+        let manifest_file = self
+            .files
+            .iter()
+            .find(|f| f.eq_ignore_ascii_case(&manifest_file))?
+            .clone();
+
+        let parsed = self
+            .read(&manifest_file)
+            .and_then(|text| ModuleManifest::parse(&text).map_err(Into::into));
+        match parsed {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                log::warn!("{}: {:?}", manifest_file, e);
+                None
+            }
+        }
+    }
+
+    /// Commands the package exports, from the manifest or, if it uses
+    /// wildcards, the tags the gallery generates from the module
+    fn exported_commands(&self, manifest: Option<&ModuleManifest>) -> Vec<String> {
+        let mut commands = manifest
+            .map(ModuleManifest::exported_commands)
+            .unwrap_or_default();
+
+        if commands.is_empty() {
+            for tag in &self.nuspec.tags {
+                let command = ["PSCmdlet_", "PSFunction_", "PSCommand_"]
+                    .iter()
+                    .find(|p| tag.starts_with(*p))
+                    .map(|p| &tag[p.len()..]);
+                if let Some(command) = command {
+                    if !commands.iter().any(|c| c.eq_ignore_ascii_case(command)) {
+                        commands.push(command.to_string());
+                    }
+                }
+            }
+        }
+
+        commands
+    }
+
+    /// Cmdlets from the help bundled in the package and its scripts, plus any
+    /// other exported commands, which are indexed by name alone
+    fn cmdlets(&mut self) -> Vec<Cmdlet> {
+        let module = self.nuspec.id.clone();
+        let version = self.nuspec.version.clone();
+        let mut cmdlets: Vec<Cmdlet> = Vec::new();
+
+        // MAML is what Get-Help would show, so it wins over markdown
+        for file in self.files_ending_with("-help.xml") {
+            let parsed = self
+                .read(&file)
+                .and_then(|text| maml::process_maml(&text, &module, &version));
+            match parsed {
+                Ok(help) => help.into_iter().for_each(|c| add_cmdlet(&mut cmdlets, c)),
+                Err(e) => log::warn!("{} {}: {:?}", module, file, e),
+            }
+        }
+
+        for file in self.files_ending_with(".md") {
+            let text = match self.read(&file) {
+                Ok(text) => text,
+                Err(e) => {
+                    log::warn!("{} {}: {:?}", module, file, e);
+                    continue;
+                }
+            };
+            // Only PlatyPS help has front matter, unlike READMEs and the like
+            if !text.starts_with("---") {
+                continue;
+            }
+            match markdown::process_markdown(&text, &module, &version) {
+                Ok(Some(cmdlet)) => add_cmdlet(&mut cmdlets, cmdlet),
+                Ok(None) => {}
+                Err(e) => log::warn!("{} {}: {:?}", module, file, e),
+            }
+        }
+
+        // Script modules often only have comment-based help
+        let manifest = self.manifest();
+        if let Some(manifest) = &manifest {
+            if !manifest.matches_version(&version) {
+                log::warn!(
+                    "{} [{}]: manifest has version {}",
+                    module,
+                    version,
+                    manifest.module_version
+                );
+            }
+        }
+        let exports = self.exported_commands(manifest.as_ref());
+        // Without a manifest, the tags list the functions
+        let functions = match &manifest {
+            Some(manifest) => manifest.functions_to_export.clone(),
+            None => exports.clone(),
+        };
+        let script_files = self
+            .files
+            .iter()
+            .filter(|f| script::is_module_script(f))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut scripts = Vec::new();
+        for file in script_files {
+            match self.read(&file) {
+                Ok(text) => scripts.push(text),
+                Err(e) => log::warn!("{} {}: {:?}", module, file, e),
+            }
+        }
+        let (functions, aliases) =
+            script::process_module_scripts(&scripts, &module, &version, &functions);
+        functions
+            .into_iter()
+            .for_each(|c| add_cmdlet(&mut cmdlets, c));
+
+        for name in exports {
+            add_cmdlet(
+                &mut cmdlets,
+                Cmdlet {
+                    module: module.clone(),
+                    module_version: version.clone(),
+                    name,
+                    aliases: Vec::new(),
+                    url: String::new(),
+                    tags: Vec::new(),
+                    synopsis: String::new(),
+                    syntax: String::new(),
+                    description: String::new(),
+                    notes: String::new(),
+                    parameters: Vec::new(),
+                    examples: Vec::new(),
+                    links: Vec::new(),
+                    module_info: Default::default(),
+                    source: Source::PSGallery,
+                },
+            );
+        }
+
+        let mut tags = self
+            .nuspec
+            .tags
+            .iter()
+            .filter(|t| !CONTENT_TAG_PREFIXES.iter().any(|p| t.starts_with(p)))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut module_info = ModuleInfo {
+            author: self.nuspec.authors.clone(),
+            description: self.nuspec.description.clone(),
+            project_uri: self.nuspec.project_url.clone(),
+            ..Default::default()
+        };
+        if let Some(manifest) = &manifest {
+            manifest.fill_info(&mut module_info);
+            manifest.merge_tags(&mut tags);
+        }
+        let url = if module_info.project_uri.is_empty() {
+            format!(
+                "https://www.powershellgallery.com/packages/{}/{}",
+                module, version
+            )
+        } else {
+            module_info.project_uri.clone()
+        };
+        for cmdlet in &mut cmdlets {
+            // Aliases can be set anywhere in the module, not just alongside
+            // the function
+            script::add_aliases(cmdlet, &aliases);
+            if let Some(manifest) = &manifest {
+                cmdlet.aliases.retain(|a| manifest.exports_alias(a));
+            }
+            if cmdlet.url.is_empty() {
+                cmdlet.url = url.clone();
+            }
+            cmdlet.tags = tags.clone();
+            cmdlet.module_info = module_info.clone();
+            cmdlet.source = Source::PSGallery;
+        }
+
+        cmdlets
+    }
+}
+
+fn process_file_nupkg(indexer: &Indexer, path: &Path) -> anyhow::Result<()> {
+    let mut package = Package::open(File::open(path)?)?;
+    if !indexer.replace_module(&package.nuspec.id, &package.nuspec.version)? {
+        return Ok(());
+    }
+
+    for cmdlet in package.cmdlets() {
+        indexer.update(&cmdlet);
+    }
+
+    Ok(())
+}
+
+fn is_nupkg_file(e: &walkdir::DirEntry) -> bool {
+    e.file_type().is_file()
+        && e.file_name()
+            .to_string_lossy()
+            .to_ascii_lowercase()
+            .ends_with(".nupkg")
+}
+
+pub fn process_directories<I>(indexer: &Indexer, directories: I) -> anyhow::Result<()>
+where
+    I: IntoIterator,
+    I::Item: AsRef<Path>,
+{
+    let dir_walker = directories.into_iter().flat_map(|d| {
+        walkdir::WalkDir::new(d)
+            .into_iter()
+            .filter_entry(|e| e.file_type().is_dir() || is_nupkg_file(e))
+    });
+
+    for dir_entry in dir_walker {
+        let de = match dir_entry {
+            Ok(de) => de,
+            Err(e) => {
+                log::warn!("{:?}", e);
+                continue;
+            }
+        };
+        if de.file_type().is_dir() {
+            continue;
+        }
+
+        log::info!("Processing {}", de.path().display());
+        if let Err(e) = process_file_nupkg(indexer, de.path()) {
+            log::warn!("{}: {:?}", de.path().display(), e);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn nupkg_cmdlets() {
+    use std::io::{Cursor, Write};
+
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let mut add_file = |name: &str, bytes: &[u8]| {
+        writer
+            .start_file(name, zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(bytes).unwrap();
+    };
+    add_file(
+        "Things.nuspec",
+        br#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://schemas.microsoft.com/packaging/2011/08/nuspec.xsd">
+  <metadata>
+    <id>Things</id>
+    <version>1.2.0</version>
+    <authors>Someone</authors>
+    <description>Manages things</description>
+    <projectUrl>https://example.com/things</projectUrl>
+    <tags>things PSModule PSIncludes_Function PSFunction_Get-Thing PSCommand_Get-Thing</tags>
+  </metadata>
+</package>"#,
+    );
+    add_file(
+        "Things.psd1",
+        b"@{\r\n  ModuleVersion = '1.2.0'\r\n  FunctionsToExport = 'Get-Thing', 'Set-Thing', 'Remove-Thing'\r\n  AliasesToExport = 'gth', 'rth'\r\n  CmdletsToExport = '*'\r\n  CompatiblePSEditions = @('Core')\r\n}\r\n",
+    );
+    let maml = "\u{feff}<helpItems schema=\"maml\"><command:command xmlns:command=\"http://schemas.microsoft.com/maml/dev/command/2004/10\"><command:details><command:name>Get-Thing</command:name></command:details></command:command></helpItems>";
+    let maml_utf16 = [0xff, 0xfe]
+        .iter()
+        .cloned()
+        .chain(
+            maml.trim_start_matches('\u{feff}')
+                .encode_utf16()
+                .flat_map(|u| u.to_le_bytes().to_vec()),
+        )
+        .collect::<Vec<u8>>();
+    add_file("en-US/Things-help.xml", &maml_utf16);
+    add_file(
+        "docs/Set-Thing.md",
+        b"---\nexternal help file: Things-help.xml\nModule Name: Things\nonline version: https://example.com/set-thing\nschema: 2.0.0\ntitle: Set-Thing\n---\n\n# Set-Thing\n\n## SYNOPSIS\nSets things.\n",
+    );
+    add_file("README.md", b"# Things\n");
+    add_file(
+        "Things.psm1",
+        b"function Remove-Thing {\n  <#\n  .SYNOPSIS\n  Removes things.\n  #>\n  param([string] $Name)\n}\n\nfunction Get-ThingHelper { }\n\nSet-Alias -Name gth -Value Get-Thing\nSet-Alias -Name rth -Value Remove-Thing\nSet-Alias -Name gthh -Value Get-ThingHelper\nSet-Alias -Name remth -Value Remove-Thing\n",
+    );
+    let reader = writer.finish().unwrap();
+
+    let mut package = Package::open(reader).unwrap();
+    assert_eq!(package.nuspec.id, "Things");
+    assert_eq!(package.nuspec.version, "1.2.0");
+
+    let cmdlets = package.cmdlets();
+    let names = cmdlets.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["Get-Thing", "Set-Thing", "Remove-Thing"]);
+    assert!(cmdlets.iter().all(|c| c.module == "Things"
+        && c.module_version == "1.2.0"
+        && c.source == Source::PSGallery
+        && c.tags == vec!["things", "PSModule"]
+        && c.module_info.author == "Someone"
+        && c.module_info.compatible_editions == vec!["Core"]));
+    assert_eq!(cmdlets[1].synopsis, "Sets things.");
+    assert_eq!(cmdlets[1].url, "https://example.com/set-thing");
+    assert_eq!(cmdlets[2].url, "https://example.com/things");
+    assert_eq!(cmdlets[2].synopsis, "Removes things.");
+    assert_eq!(cmdlets[0].aliases, vec!["gth"]);
+    assert_eq!(cmdlets[2].aliases, vec!["rth"]);
+}
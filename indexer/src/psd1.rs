@@ -0,0 +1,398 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// A value in a PowerShell data file, eg. a module manifest. Only literals are
+/// supported, which is all data files are allowed to contain.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Psd1Value {
+    String(String),
+
+    /// Numbers, variables like $true, and anything else unquoted, as written
+    Bare(String),
+
+    Array(Vec<Psd1Value>),
+
+    /// Keys and values in the order they were written
+    Hashtable(Vec<(String, Psd1Value)>),
+}
+
+impl Psd1Value {
+    /// Value for a hashtable key, ignoring case like PowerShell does
+    pub fn get(&self, key: &str) -> Option<&Psd1Value> {
+        match self {
+            Psd1Value::Hashtable(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Psd1Value::String(s) | Psd1Value::Bare(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Non-empty strings in a value that may be a single string or an array,
+    /// eg. FunctionsToExport = 'Get-Thing' or @('Get-Thing', 'Set-Thing')
+    pub fn strings(&self) -> Vec<&str> {
+        match self {
+            Psd1Value::Array(values) => values.iter().flat_map(Psd1Value::strings).collect(),
+            Psd1Value::String(s) if !s.trim().is_empty() => vec![s.trim()],
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Psd1Error {
+    UnexpectedEnd,
+    UnexpectedChar(char, usize),
+}
+
+impl Display for Psd1Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Psd1Error::UnexpectedEnd => f.write_str("Unexpected end of data file"),
+            Psd1Error::UnexpectedChar(c, idx) => {
+                write!(f, "Unexpected '{}' at character {} of data file", c, idx)
+            }
+        }
+    }
+}
+
+impl Error for Psd1Error {}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn next(&mut self) -> Result<char, Psd1Error> {
+        let c = self.peek().ok_or(Psd1Error::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Psd1Error> {
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(Psd1Error::UnexpectedChar(c, self.pos - 1)),
+        }
+    }
+
+    /// Skip whitespace, including newlines, and comments
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.pos += 1;
+            } else if c == '<' && self.peek_at(1) == Some('#') {
+                self.pos += 2;
+                while self.peek().is_some()
+                    && !(self.peek() == Some('#') && self.peek_at(1) == Some('>'))
+                {
+                    self.pos += 1;
+                }
+                self.pos += 2;
+            } else if c == '#' {
+                while self.peek().map(|c| c != '\n').unwrap_or(false) {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Skip whitespace and any of the given separators
+    fn skip_separators(&mut self, separators: &[char]) {
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(c) if separators.contains(&c) => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn single_quoted(&mut self) -> Result<String, Psd1Error> {
+        let mut s = String::new();
+        loop {
+            match self.next()? {
+                // Quotes are escaped by doubling them
+                '\'' if self.peek() == Some('\'') => {
+                    self.pos += 1;
+                    s.push('\'');
+                }
+                '\'' => return Ok(s),
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn double_quoted(&mut self) -> Result<String, Psd1Error> {
+        let mut s = String::new();
+        loop {
+            match self.next()? {
+                '"' if self.peek() == Some('"') => {
+                    self.pos += 1;
+                    s.push('"');
+                }
+                '"' => return Ok(s),
+                '`' => match self.next()? {
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    '0' => s.push('\0'),
+                    c => s.push(c),
+                },
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Here-string, eg. @' ... '@, with the closing quote at the start of a line
+    fn here_string(&mut self, quote: char) -> Result<String, Psd1Error> {
+        while self.next()? != '\n' {}
+
+        let mut s = String::new();
+        loop {
+            let at_line_start = s.is_empty() || s.ends_with('\n');
+            let c = self.next()?;
+            if at_line_start && c == quote && self.peek() == Some('@') {
+                self.pos += 1;
+                if s.ends_with('\n') {
+                    s.pop();
+                }
+                if s.ends_with('\r') {
+                    s.pop();
+                }
+                return Ok(s);
+            }
+            s.push(c);
+        }
+    }
+
+    fn bare(&mut self) -> String {
+        let mut s = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || ";,)}=".contains(c) {
+                break;
+            }
+            s.push(c);
+            self.pos += 1;
+        }
+
+        s
+    }
+
+    fn hashtable(&mut self) -> Result<Psd1Value, Psd1Error> {
+        let mut entries = Vec::new();
+        loop {
+            self.skip_separators(&[';']);
+            if self.peek() == Some('}') {
+                self.pos += 1;
+                return Ok(Psd1Value::Hashtable(entries));
+            }
+
+            let key = match self.peek().ok_or(Psd1Error::UnexpectedEnd)? {
+                '\'' => {
+                    self.pos += 1;
+                    self.single_quoted()?
+                }
+                '"' => {
+                    self.pos += 1;
+                    self.double_quoted()?
+                }
+                c => {
+                    let key = self.bare();
+                    if key.is_empty() {
+                        return Err(Psd1Error::UnexpectedChar(c, self.pos));
+                    }
+                    key
+                }
+            };
+            self.skip_whitespace();
+            self.expect('=')?;
+            let value = self.expression()?;
+            entries.push((key, value));
+        }
+    }
+
+    fn array(&mut self, end: char) -> Result<Psd1Value, Psd1Error> {
+        let mut values = Vec::new();
+        loop {
+            self.skip_separators(&[',', ';']);
+            if self.peek() == Some(end) {
+                self.pos += 1;
+                return Ok(Psd1Value::Array(values));
+            }
+            values.push(self.value()?);
+        }
+    }
+
+    fn value(&mut self) -> Result<Psd1Value, Psd1Error> {
+        self.skip_whitespace();
+        match self.peek().ok_or(Psd1Error::UnexpectedEnd)? {
+            '\'' => {
+                self.pos += 1;
+                self.single_quoted().map(Psd1Value::String)
+            }
+            '"' => {
+                self.pos += 1;
+                self.double_quoted().map(Psd1Value::String)
+            }
+            '@' => {
+                self.pos += 1;
+                match self.next()? {
+                    '{' => self.hashtable(),
+                    '(' => self.array(')'),
+                    quote @ '\'' | quote @ '"' => self.here_string(quote).map(Psd1Value::String),
+                    c => Err(Psd1Error::UnexpectedChar(c, self.pos - 1)),
+                }
+            }
+            '(' => {
+                self.pos += 1;
+                let value = self.expression()?;
+                self.skip_whitespace();
+                self.expect(')')?;
+                Ok(value)
+            }
+            // Casts, eg. [version]'1.0', don't change what's written
+            '[' => {
+                // Types can have brackets of their own, eg. [string[]]
+                let mut depth = 0;
+                loop {
+                    match self.next()? {
+                        '[' => depth += 1,
+                        ']' if depth == 1 => break,
+                        ']' => depth -= 1,
+                        _ => {}
+                    }
+                }
+                self.value()
+            }
+            c => {
+                let bare = self.bare();
+                if bare.is_empty() {
+                    Err(Psd1Error::UnexpectedChar(c, self.pos))
+                } else {
+                    Ok(Psd1Value::Bare(bare))
+                }
+            }
+        }
+    }
+
+    /// A value, or several separated by commas, which make an array
+    fn expression(&mut self) -> Result<Psd1Value, Psd1Error> {
+        let first = self.value()?;
+        self.skip_whitespace();
+        if self.peek() != Some(',') {
+            return Ok(first);
+        }
+
+        let mut values = vec![first];
+        while self.peek() == Some(',') {
+            self.pos += 1;
+            values.push(self.value()?);
+            self.skip_whitespace();
+        }
+
+        Ok(Psd1Value::Array(values))
+    }
+}
+
+/// Parse a PowerShell data file, which contains a single hashtable
+pub fn parse(text: &str) -> Result<Psd1Value, Psd1Error> {
+    let mut parser = Parser {
+        chars: text.trim_start_matches('\u{feff}').chars().collect(),
+        pos: 0,
+    };
+    parser.skip_whitespace();
+    parser.expect('@')?;
+    parser.expect('{')?;
+
+    parser.hashtable()
+}
+
+#[test]
+fn parse_manifest() {
+    let manifest = parse(
+        r#"
+        <#
+            Module manifest for module 'Things'
+        #>
+        @{
+            RootModule = 'Things.psm1'
+            ModuleVersion = '1.2.0'
+            'GUID' = "d0a9150d-b6a4-4b17-a325-e3a24fed0aa9"
+            Author = 'It''s me' ; CompanyName = "Things `"Inc`""
+            PowerShellVersion = 5.1
+            FunctionsToExport = @(
+                'Get-Thing',   # gets things
+                'Set-Thing'
+                "Remove-Thing"
+            )
+            CmdletsToExport = @()
+            AliasesToExport = [string[]]('gth', 'sth')
+            PrivateData = @{
+                PSData = @{
+                    Tags = @('things', 'stuff')
+                    ProjectUri = 'https://example.com/things'
+                    ReleaseNotes = @'
+First line
+Second line
+'@
+                }
+            }
+        }
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        manifest.get("moduleversion").unwrap().strings(),
+        vec!["1.2.0"]
+    );
+    assert_eq!(manifest.get("Author").unwrap().strings(), vec!["It's me"]);
+    assert_eq!(
+        manifest.get("CompanyName").unwrap().strings(),
+        vec!["Things \"Inc\""]
+    );
+    assert_eq!(
+        manifest.get("FunctionsToExport").unwrap().strings(),
+        vec!["Get-Thing", "Set-Thing", "Remove-Thing"]
+    );
+    assert!(manifest
+        .get("CmdletsToExport")
+        .unwrap()
+        .strings()
+        .is_empty());
+    assert_eq!(
+        manifest.get("AliasesToExport").unwrap().strings(),
+        vec!["gth", "sth"]
+    );
+    let ps_data = manifest
+        .get("PrivateData")
+        .and_then(|p| p.get("PSData"))
+        .unwrap();
+    assert_eq!(
+        ps_data.get("Tags").unwrap().strings(),
+        vec!["things", "stuff"]
+    );
+    assert_eq!(
+        ps_data.get("ReleaseNotes").unwrap().strings(),
+        vec!["First line\nSecond line"]
+    );
+}
@@ -1,14 +1,18 @@
 use anyhow::Context;
 use rayon::prelude::*;
+use std::convert::TryFrom;
 use std::fs::File;
 use std::{
     io::{BufReader, Read},
     path,
 };
 
-use crate::cmdlet::Cmdlet;
 use crate::error::FindCmdletError;
 use crate::indexer::Indexer;
+use crate::manifest::ModuleManifest;
+use crate::script;
+use crate::syntax::{is_common_parameter, ParameterSetSyntax, SyntaxParameter};
+use find_cmdlet_index::cmdlet::{Cmdlet, Example, ModuleInfo, Parameter, Source};
 use path::{Path, PathBuf};
 
 trait TryIndex {
@@ -88,6 +92,352 @@ fn read_json<P: AsRef<path::Path>>(path: P) -> anyhow::Result<serde_json::Value>
     Ok(json)
 }
 
+fn json_type_name(val: &serde_json::Value) -> Option<String> {
+    match val {
+        serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        serde_json::Value::Object(_) => val
+            .try_index("FullName", "Name")
+            .as_str()
+            .map(|s| s.trim().to_string()),
+        _ => None,
+    }
+}
+
+fn json_position(val: &serde_json::Value) -> Option<u32> {
+    // Named parameters have a position of int.MinValue
+    val.as_i64().and_then(|p| u32::try_from(p).ok())
+}
+
+fn json_syntax_parameter(
+    param: &serde_json::Value,
+    set_metadata: &serde_json::Value,
+) -> Option<SyntaxParameter> {
+    let name = param.try_index("Name", "name").as_str()?.trim().to_string();
+    let type_name = json_type_name(param.try_index("ParameterType", "parameterType"));
+    let switch = param
+        .try_index("SwitchParameter", "switchParameter")
+        .as_bool()
+        .unwrap_or(false)
+        || type_name
+            .as_ref()
+            .map(|t| t.ends_with("SwitchParameter"))
+            .unwrap_or(false);
+
+    Some(SyntaxParameter {
+        name,
+        type_name,
+        mandatory: set_metadata
+            .try_index("IsMandatory", "isMandatory")
+            .as_bool()
+            .unwrap_or(false),
+        position: json_position(set_metadata.try_index("Position", "position")),
+        switch,
+    })
+}
+
+/// Find the parameters in a parameter set using the command's Parameters
+/// dictionary, used when the ParameterSets entries were truncated by
+/// ConvertTo-Json
+fn json_parameters_in_set(
+    command_json: &serde_json::Value,
+    set_name: &str,
+) -> Vec<SyntaxParameter> {
+    let parameters = match command_json
+        .try_index("Parameters", "parameters")
+        .as_object()
+    {
+        Some(parameters) => parameters,
+        None => return vec![],
+    };
+
+    parameters
+        .values()
+        .filter_map(|param| {
+            let sets = param.try_index("ParameterSets", "parameterSets");
+            let set_metadata = match sets.as_object() {
+                Some(sets) => sets
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(set_name))
+                    .or_else(|| sets.iter().find(|(k, _)| *k == "__AllParameterSets"))
+                    .map(|(_, v)| v)?,
+                // Not enough depth to know which sets the parameter is in
+                None => &serde_json::Value::Null,
+            };
+
+            json_syntax_parameter(param, set_metadata)
+        })
+        .collect()
+}
+
+fn json_parameter_set_syntax(name: &str, parameters: Vec<SyntaxParameter>) -> String {
+    let common_parameters = parameters.iter().any(|p| is_common_parameter(&p.name));
+    let parameters = parameters
+        .into_iter()
+        .filter(|p| !is_common_parameter(&p.name))
+        .collect();
+
+    ParameterSetSyntax {
+        command: name.to_string(),
+        parameters,
+        common_parameters,
+    }
+    .to_string()
+}
+
+/// Build PowerShell style syntax lines, one per parameter set, from the output
+/// of Get-Command
+fn build_syntax(name: &str, command_json: &serde_json::Value) -> String {
+    let parameter_sets = command_json
+        .try_index("ParameterSets", "parameterSets")
+        .as_array()
+        .map(|sets| sets.as_slice())
+        .unwrap_or(&[]);
+
+    let lines = if parameter_sets.is_empty() {
+        if command_json
+            .try_index("Parameters", "parameters")
+            .as_object()
+            .is_none()
+        {
+            return String::new();
+        }
+
+        let parameters = json_parameters_in_set(command_json, "__AllParameterSets");
+        vec![json_parameter_set_syntax(name, parameters)]
+    } else {
+        parameter_sets
+            .iter()
+            .filter_map(|set| match set {
+                // ConvertTo-Json ran out of depth, so this is the parameter
+                // set's ToString(), which is already in syntax form
+                serde_json::Value::String(s) => Some(format!("{} {}", name, s.trim())),
+                serde_json::Value::Object(_) => {
+                    let set_name = set.try_index("Name", "name").as_str().unwrap_or("");
+                    let parameters = match set.try_index("Parameters", "parameters").as_array() {
+                        Some(parameters) => parameters
+                            .iter()
+                            .filter_map(|p| json_syntax_parameter(p, p))
+                            .collect(),
+                        None => json_parameters_in_set(command_json, set_name),
+                    };
+
+                    Some(json_parameter_set_syntax(name, parameters))
+                }
+                _ => None,
+            })
+            .collect()
+    };
+
+    lines.join("\n")
+}
+
+/// Get help text from a Get-Help description, which is a list of paragraphs
+fn json_help_text(val: &serde_json::Value) -> String {
+    match val {
+        serde_json::Value::String(s) => s.trim().to_string(),
+        serde_json::Value::Array(paragraphs) => paragraphs
+            .iter()
+            .filter_map(|p| p.try_index("text", "Text").as_str())
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        serde_json::Value::Object(_) => val
+            .try_index("text", "Text")
+            .as_str()
+            .unwrap_or("")
+            .trim()
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Get a list from a value that ConvertTo-Json may have unwrapped to a single
+/// item
+fn json_list(val: &serde_json::Value) -> &[serde_json::Value] {
+    match val {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Null => &[],
+        _ => std::slice::from_ref(val),
+    }
+}
+
+fn find_parameter<'a>(parameters: &'a mut Vec<Parameter>, name: &str) -> &'a mut Parameter {
+    let idx = match parameters
+        .iter()
+        .position(|p| p.name.eq_ignore_ascii_case(name))
+    {
+        Some(idx) => idx,
+        None => {
+            parameters.push(Parameter {
+                name: name.to_string(),
+                ..Default::default()
+            });
+            parameters.len() - 1
+        }
+    };
+
+    &mut parameters[idx]
+}
+
+fn json_update_parameter(
+    parameter: &mut Parameter,
+    param: &serde_json::Value,
+    set_name: &str,
+    set_metadata: &serde_json::Value,
+) {
+    if parameter.type_name.is_empty() {
+        if let Some(type_name) = json_type_name(param.try_index("ParameterType", "parameterType")) {
+            parameter.type_name = type_name;
+        }
+    }
+    for alias in json_list(param.try_index("Aliases", "aliases")) {
+        if let Some(alias) = alias.as_str() {
+            parameter.add_aliases(alias);
+        }
+    }
+
+    parameter.mandatory |= set_metadata
+        .try_index("IsMandatory", "isMandatory")
+        .as_bool()
+        .unwrap_or(false);
+    parameter.pipeline_by_value |= set_metadata
+        .try_index("ValueFromPipeline", "valueFromPipeline")
+        .as_bool()
+        .unwrap_or(false);
+    parameter.pipeline_by_property_name |= set_metadata
+        .try_index(
+            "ValueFromPipelineByPropertyName",
+            "valueFromPipelineByPropertyName",
+        )
+        .as_bool()
+        .unwrap_or(false);
+    if parameter.description.is_empty() {
+        if let Some(help_message) = set_metadata
+            .try_index("HelpMessage", "helpMessage")
+            .as_str()
+        {
+            parameter.description = help_message.trim().to_string();
+        }
+    }
+
+    if !set_name.is_empty()
+        && set_name != "__AllParameterSets"
+        && !parameter.parameter_sets.iter().any(|s| s == set_name)
+    {
+        parameter.parameter_sets.push(set_name.to_string());
+    }
+}
+
+/// Build the list of parameters for a command, using Get-Command for
+/// parameter metadata and Get-Help for the help text
+fn build_parameters(
+    command_json: &serde_json::Value,
+    help_json: &serde_json::Value,
+) -> Vec<Parameter> {
+    let mut parameters = Vec::new();
+
+    let parameter_sets = json_list(command_json.try_index("ParameterSets", "parameterSets"));
+    for set in parameter_sets {
+        let set_name = set.try_index("Name", "name").as_str().unwrap_or("");
+        for param in json_list(set.try_index("Parameters", "parameters")) {
+            if let Some(name) = param.try_index("Name", "name").as_str() {
+                let parameter = find_parameter(&mut parameters, name.trim());
+                json_update_parameter(parameter, param, set_name, param);
+            }
+        }
+    }
+
+    if let Some(params) = command_json
+        .try_index("Parameters", "parameters")
+        .as_object()
+    {
+        for (name, param) in params {
+            let parameter = find_parameter(&mut parameters, name.trim());
+            let sets = param.try_index("ParameterSets", "parameterSets");
+            match sets.as_object() {
+                Some(sets) => {
+                    for (set_name, set_metadata) in sets {
+                        json_update_parameter(parameter, param, set_name, set_metadata);
+                    }
+                }
+                None => json_update_parameter(parameter, param, "", &serde_json::Value::Null),
+            }
+        }
+    }
+
+    let help_parameters = json_list(
+        help_json
+            .try_index("parameters", "Parameters")
+            .try_index("parameter", "Parameter"),
+    );
+    for param in help_parameters {
+        let name = match param.try_index("name", "Name").as_str() {
+            Some(name) => name.trim(),
+            None => continue,
+        };
+        let parameter = find_parameter(&mut parameters, name);
+
+        let description = json_help_text(param.try_index("description", "Description"));
+        if !description.is_empty() {
+            parameter.description = description;
+        }
+        if parameter.type_name.is_empty() {
+            let type_name = param.try_index("type", "Type");
+            if let Some(type_name) = type_name.try_index("name", "Name").as_str() {
+                parameter.type_name = type_name.trim().to_string();
+            }
+        }
+        if let Some(required) = param.try_index("required", "Required").as_str() {
+            parameter.mandatory |= required.trim().eq_ignore_ascii_case("true");
+        }
+        if let Some(pipeline_input) = param.try_index("pipelineInput", "PipelineInput").as_str() {
+            parameter.set_pipeline_input(pipeline_input);
+        }
+        if let Some(aliases) = param.try_index("aliases", "Aliases").as_str() {
+            parameter.add_aliases(aliases);
+        }
+    }
+
+    parameters.retain(|p| !is_common_parameter(&p.name));
+
+    parameters
+}
+
+/// Build the list of examples from Get-Help
+fn build_examples(help_json: &serde_json::Value) -> Vec<Example> {
+    let examples = json_list(
+        help_json
+            .try_index("examples", "Examples")
+            .try_index("example", "Example"),
+    );
+
+    examples
+        .iter()
+        .filter_map(|example| {
+            let code = json_help_text(example.try_index("code", "Code"));
+            let remarks = json_help_text(example.try_index("remarks", "Remarks"));
+            if code.is_empty() && remarks.is_empty() {
+                return None;
+            }
+
+            // Titles are surrounded by dashes, eg. ------ Example 1 ------
+            let title = example
+                .try_index("title", "Title")
+                .as_str()
+                .unwrap_or("")
+                .trim_matches(|c: char| c == '-' || c.is_whitespace())
+                .to_string();
+
+            Some(Example {
+                title,
+                code,
+                remarks,
+            })
+        })
+        .collect()
+}
+
 fn process_file_json(
     module_metadata: &ModuleMetaData,
     module_json: serde_json::Value,
@@ -147,7 +497,7 @@ fn process_file_json(
         .trim()
         .to_string();
 
-    let syntax = "".to_string(); // TODO build from parameters
+    let syntax = build_syntax(&name, &command_json);
 
     let description = help_json.try_index("description", "Description")[0]
         .try_index("text", "Text")
@@ -165,19 +515,101 @@ fn process_file_json(
 
     let notes = "".to_string(); // TODO Maybe remarks?
 
+    // Find-Module has the most detail, but builtin modules only have Get-Module
+    let mut module_info = module_metadata.info.clone();
+    let installed_info = json_module_info(&module_json);
+    if module_info.author.is_empty() {
+        module_info.author = installed_info.author;
+    }
+    if module_info.description.is_empty() {
+        module_info.description = installed_info.description;
+    }
+    if module_info.project_uri.is_empty() {
+        module_info.project_uri = installed_info.project_uri;
+    }
+    if let Some(manifest) = &module_metadata.manifest {
+        manifest.fill_info(&mut module_info);
+        manifest.merge_tags(&mut tags);
+    }
+
+    let parameters = build_parameters(&command_json, &help_json);
+    let examples = build_examples(&help_json);
+
+    // The builtin runner tags its modules, anything else not from the gallery
+    // was installed as a Windows feature
+    let source = if module_metadata.from_gallery {
+        Source::PSGallery
+    } else if tags.iter().any(|t| t.eq_ignore_ascii_case("builtin")) {
+        Source::Builtin
+    } else {
+        Source::Rsat
+    };
+
     Ok(Cmdlet {
         module: module_metadata.name.clone(),
         module_version: module_metadata.version.clone(),
         name,
+        aliases: Vec::new(),
         url,
         tags,
         synopsis,
         syntax,
         description,
         notes,
+        parameters,
+        examples,
+        links: Vec::new(),
+        module_info,
+        source,
     })
 }
 
+/// The alias and the command it resolves to, if the command json is for an
+/// alias
+fn json_alias(command_json: &serde_json::Value) -> Option<(String, String)> {
+    // ConvertTo-Json writes enums as numbers, 1 being CommandTypes.Alias
+    let is_alias = match command_json.try_index("CommandType", "commandType") {
+        serde_json::Value::Number(n) => n.as_u64() == Some(1),
+        serde_json::Value::String(s) => s.eq_ignore_ascii_case("Alias"),
+        _ => false,
+    };
+    if !is_alias {
+        return None;
+    }
+
+    let name = command_json.try_index("Name", "name").as_str()?.trim();
+    let command = command_json
+        .try_index4(
+            "ResolvedCommandName",
+            "resolvedCommandName",
+            "Definition",
+            "definition",
+        )
+        .as_str()?
+        .trim();
+    if name.is_empty() || command.is_empty() {
+        return None;
+    }
+
+    Some((name.to_string(), command.to_string()))
+}
+
+/// Aliases the builtin runner found for a module's commands, which it writes
+/// alongside them since aliases to snap-in cmdlets aren't exported commands
+fn read_aliases(path: &Path) -> Vec<(String, String)> {
+    if !path.exists() {
+        return Vec::new();
+    }
+
+    match read_json(path) {
+        Ok(json) => json_list(&json).iter().filter_map(json_alias).collect(),
+        Err(e) => {
+            log::warn!("{:?}", e);
+            Vec::new()
+        }
+    }
+}
+
 fn is_json_file(e: &walkdir::DirEntry) -> bool {
     let is_dir = e.file_type().is_dir();
     let is_json = e.file_type().is_file() && e.file_name().to_string_lossy().ends_with(".json");
@@ -190,6 +622,107 @@ struct ModuleMetaData {
     name: String,
     version: String,
     docs_dir: PathBuf,
+
+    /// Where the scraper saved the module, modules/<Name>/<Version>
+    module_dir: PathBuf,
+
+    info: ModuleInfo,
+
+    /// Manifest of the saved module, which only gallery modules have
+    manifest: Option<ModuleManifest>,
+
+    /// Whether the metadata came from Find-Module, rather than being written
+    /// by the builtin or RSAT runners
+    from_gallery: bool,
+}
+
+impl ModuleMetaData {
+    /// Where the scraper wrote the module's json, docs/<Name>/<Version>
+    fn doc_dir(&self) -> PathBuf {
+        self.docs_dir.join(&self.name).join(&self.version)
+    }
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) date
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Get a date as YYYY-MM-DD, from either the /Date(ms)/ format used by
+/// Windows PowerShell's ConvertTo-Json, or an ISO 8601 string
+fn json_date(val: &serde_json::Value) -> String {
+    let date = match val.as_str() {
+        Some(date) => date.trim(),
+        None => return String::new(),
+    };
+
+    if let Some(millis) = date
+        .strip_prefix("/Date(")
+        .and_then(|d| d.strip_suffix(")/"))
+        .and_then(|d| d.split(&['+', '-'][..]).next())
+        .and_then(|d| d.parse::<i64>().ok())
+    {
+        let (year, month, day) = civil_from_days(millis.div_euclid(86_400_000));
+        return format!("{:04}-{:02}-{:02}", year, month, day);
+    }
+
+    date.get(..10).unwrap_or(date).to_string()
+}
+
+fn json_module_info(json: &serde_json::Value) -> ModuleInfo {
+    let text = |idx1, idx2| {
+        json.try_index(idx1, idx2)
+            .as_str()
+            .unwrap_or("")
+            .trim()
+            .to_string()
+    };
+
+    ModuleInfo {
+        author: text("Author", "author"),
+        description: text("Description", "description"),
+        project_uri: text("ProjectUri", "projectUri"),
+        published: json_date(json.try_index("PublishedDate", "publishedDate")),
+        company_name: text("CompanyName", "companyName"),
+        license_uri: text("LicenseUri", "licenseUri"),
+        ..Default::default()
+    }
+}
+
+/// Find and read the manifest of a module saved by the scraper, which is at
+/// <Name>/<Version>/<Name>.psd1 in the module directory
+fn find_manifest(module_dir: &Path, name: &str) -> Option<ModuleManifest> {
+    let file_name = format!("{}.psd1", name);
+    let path = walkdir::WalkDir::new(module_dir)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(Result::ok)
+        .find(|de| {
+            de.file_type().is_file()
+                && de
+                    .file_name()
+                    .to_string_lossy()
+                    .eq_ignore_ascii_case(&file_name)
+        })?
+        .into_path();
+
+    match ModuleManifest::read(&path) {
+        Ok(manifest) => Some(manifest),
+        Err(e) => {
+            log::warn!("{}: {:?}", path.display(), e);
+            None
+        }
+    }
 }
 
 fn process_metadata_json<P: AsRef<path::Path>>(path: P) -> anyhow::Result<ModuleMetaData> {
@@ -206,18 +739,34 @@ fn process_metadata_json<P: AsRef<path::Path>>(path: P) -> anyhow::Result<Module
         .ok_or(FindCmdletError::MissingModuleVersion)?
         .to_string();
 
-    let docs_dir = path
+    let data_dir = path
         .as_ref()
         .parent()
         .expect("file cannot exist outside of directory")
         .parent()
-        .expect("parent directory must exist")
-        .join("docs");
+        .expect("parent directory must exist");
+    let docs_dir = data_dir.join("docs");
+    let module_dir = data_dir.join("modules").join(&name).join(&version);
+    let manifest = find_manifest(&module_dir, &name);
+    if let Some(manifest) = &manifest {
+        if !manifest.matches_version(&version) {
+            log::warn!(
+                "{} [{}]: manifest has version {}",
+                name,
+                version,
+                manifest.module_version
+            );
+        }
+    }
 
     Ok(ModuleMetaData {
         name,
         version,
         docs_dir,
+        module_dir,
+        info: json_module_info(&json),
+        manifest,
+        from_gallery: !json.try_index("Repository", "repository").is_null(),
     })
 }
 
@@ -266,14 +815,8 @@ struct PSGalleryCmdletFileIter {
 }
 
 impl PSGalleryCmdletFileIter {
-    pub fn new<P: AsRef<path::Path>>(
-        module_metadata: ModuleMetaData,
-        docs_root: P,
-    ) -> anyhow::Result<Self> {
-        let doc_dir = docs_root
-            .as_ref()
-            .join(&module_metadata.name)
-            .join(&module_metadata.version);
+    pub fn new(module_metadata: ModuleMetaData) -> anyhow::Result<Self> {
+        let doc_dir = module_metadata.doc_dir();
 
         let module_path = doc_dir.join("mod.json");
         let cmd_dir = doc_dir.join("commands");
@@ -380,6 +923,50 @@ impl Iterator for PSGalleryCmdletFileIter {
     }
 }
 
+/// Index the functions in a saved module's scripts, for script modules the
+/// scraper couldn't get help for
+fn process_module_scripts(module_metadata: &ModuleMetaData, indexer: &Indexer) {
+    let functions = module_metadata
+        .manifest
+        .as_ref()
+        .map(|m| m.functions_to_export.clone())
+        .unwrap_or_default();
+    let cmdlets = script::process_module_dir(
+        &module_metadata.module_dir,
+        &module_metadata.name,
+        &module_metadata.version,
+        &functions,
+    );
+
+    let mut tags = Vec::new();
+    let mut module_info = module_metadata.info.clone();
+    if let Some(manifest) = &module_metadata.manifest {
+        manifest.merge_tags(&mut tags);
+        manifest.fill_info(&mut module_info);
+    }
+    let url = if module_info.project_uri.is_empty() {
+        format!(
+            "https://www.powershellgallery.com/packages/{}/{}",
+            module_metadata.name, module_metadata.version
+        )
+    } else {
+        module_info.project_uri.clone()
+    };
+
+    for mut cmdlet in cmdlets {
+        if cmdlet.url.is_empty() {
+            cmdlet.url = url.clone();
+        }
+        if let Some(manifest) = &module_metadata.manifest {
+            cmdlet.aliases.retain(|a| manifest.exports_alias(a));
+        }
+        cmdlet.tags = tags.clone();
+        cmdlet.module_info = module_info.clone();
+        cmdlet.source = Source::PSGallery;
+        indexer.update(&cmdlet);
+    }
+}
+
 fn process_module_metadata(
     module_metadata: &ModuleMetaData,
     indexer: &Indexer,
@@ -390,10 +977,28 @@ fn process_module_metadata(
         module_metadata.version
     );
 
-    let iter = PSGalleryCmdletFileIter::new(module_metadata.clone(), &module_metadata.docs_dir)?;
+    if !indexer.replace_module(&module_metadata.name, &module_metadata.version)? {
+        log::info!(
+            "Skipping {} [{}], already indexed",
+            module_metadata.name,
+            module_metadata.version
+        );
+        return Ok(());
+    }
+
+    let iter = match PSGalleryCmdletFileIter::new(module_metadata.clone()) {
+        Ok(iter) => iter,
+        Err(e) if module_metadata.module_dir.is_dir() => {
+            log::warn!("{:?}, indexing comment-based help instead", e);
+            process_module_scripts(module_metadata, indexer);
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
     //match iter {
     //    Ok(iter) => {
-    iter.collect::<Vec<_>>()
+    let files = iter
+        .collect::<Vec<_>>()
         .into_par_iter()
         .filter_map(|files| match files {
             Ok(files) => {
@@ -445,10 +1050,27 @@ fn process_module_metadata(
                 None
             }
         })
+        .collect::<Vec<_>>();
+
+    // Exported aliases have their own command json, but with the help of the
+    // command they resolve to, so are added to that command instead
+    let mut aliases = read_aliases(&module_metadata.doc_dir().join("aliases.json"));
+    aliases.extend(
+        files
+            .iter()
+            .filter_map(|(_, command_json, _)| json_alias(command_json)),
+    );
+
+    files
+        .into_par_iter()
+        .filter(|(_, command_json, _)| json_alias(command_json).is_none())
         .for_each(|(module_json, command_json, help_json)| {
             let cmdlet = process_file_json(&module_metadata, module_json, command_json, help_json);
             match cmdlet {
-                Ok(cmdlet) => indexer.update(&cmdlet),
+                Ok(mut cmdlet) => {
+                    script::add_aliases(&mut cmdlet, &aliases);
+                    indexer.update(&cmdlet)
+                }
                 Err(e) => log::warn!("{:?}", e),
             }
         });
@@ -503,3 +1125,41 @@ where
 
     Ok(())
 }
+
+#[test]
+fn json_dates() {
+    let test_cases = &[
+        ("/Date(1592870400000)/", "2020-06-23"),
+        ("/Date(951782400000+0000)/", "2000-02-29"),
+        ("2020-06-23T10:11:12Z", "2020-06-23"),
+        ("", ""),
+    ];
+
+    for (date, expected) in test_cases {
+        assert_eq!(&json_date(&serde_json::json!(date)), expected);
+    }
+}
+
+#[test]
+fn json_aliases() {
+    let alias = serde_json::json!({
+        "Name": "gci",
+        "CommandType": 1,
+        "Definition": "Get-ChildItem",
+        "ResolvedCommandName": "Get-ChildItem"
+    });
+    assert_eq!(
+        json_alias(&alias),
+        Some(("gci".to_string(), "Get-ChildItem".to_string()))
+    );
+
+    let alias =
+        serde_json::json!({"Name": "gth", "CommandType": "Alias", "Definition": "Get-Thing"});
+    assert_eq!(
+        json_alias(&alias),
+        Some(("gth".to_string(), "Get-Thing".to_string()))
+    );
+
+    let cmdlet = serde_json::json!({"Name": "Get-ChildItem", "CommandType": 8});
+    assert_eq!(json_alias(&cmdlet), None);
+}
@@ -0,0 +1,1110 @@
+use crate::manifest::wildcard_match;
+use crate::syntax::{is_common_parameter, ParameterSetSyntax, SyntaxParameter};
+use crate::text::decode_text;
+use find_cmdlet_index::cmdlet::{Cmdlet, Example, Link, Parameter, Source};
+use std::path::Path;
+
+/// Keywords that start a section of comment-based help, see about_Comment_Based_Help
+const HELP_KEYWORDS: &[&str] = &[
+    "SYNOPSIS",
+    "DESCRIPTION",
+    "PARAMETER",
+    "EXAMPLE",
+    "INPUTS",
+    "OUTPUTS",
+    "NOTES",
+    "LINK",
+    "COMPONENT",
+    "ROLE",
+    "FUNCTIONALITY",
+    "FORWARDHELPTARGETNAME",
+    "FORWARDHELPCATEGORY",
+    "REMOTEHELPRUNSPACE",
+    "EXTERNALHELP",
+];
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    /// Text of a block comment, or of consecutive line comments
+    Comment(String),
+
+    /// Anything unquoted, eg. keywords, names, variables and operators
+    Word(String),
+
+    String(String),
+
+    /// One of ( ) [ ] { } , ; =
+    Punct(char),
+}
+
+struct Spanned {
+    token: Token,
+    line: usize,
+    end_line: usize,
+    line_comment: bool,
+}
+
+/// Split a script into just enough tokens to find functions, their param()
+/// blocks and comments, without being confused by brackets in strings
+fn tokenize(text: &str) -> Vec<Spanned> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens: Vec<Spanned> = Vec::new();
+    let mut pos = 0;
+    let mut line = 1;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        let start_line = line;
+        let peek = |offset: usize| chars.get(pos + offset).copied();
+
+        if c == '\n' {
+            line += 1;
+            pos += 1;
+        } else if c.is_whitespace() {
+            pos += 1;
+        } else if c == '`' {
+            // Line continuation, or an escaped character
+            if peek(1) == Some('\n') {
+                line += 1;
+            }
+            pos += 2;
+        } else if c == '<' && peek(1) == Some('#') {
+            let start = pos + 2;
+            pos = start;
+            while pos < chars.len() && !(chars[pos] == '#' && peek_at(&chars, pos + 1) == Some('>'))
+            {
+                if chars[pos] == '\n' {
+                    line += 1;
+                }
+                pos += 1;
+            }
+            let comment = chars[start..pos.min(chars.len())].iter().collect();
+            pos += 2;
+            tokens.push(Spanned {
+                token: Token::Comment(comment),
+                line: start_line,
+                end_line: line,
+                line_comment: false,
+            });
+        } else if c == '#' {
+            let start = pos + 1;
+            while pos < chars.len() && chars[pos] != '\n' {
+                pos += 1;
+            }
+            let comment = chars[start..pos].iter().collect::<String>();
+
+            // Consecutive line comments are one block of help
+            match tokens.last_mut() {
+                Some(Spanned {
+                    token: Token::Comment(previous),
+                    end_line,
+                    line_comment: true,
+                    ..
+                }) if *end_line + 1 == line => {
+                    previous.push('\n');
+                    previous.push_str(&comment);
+                    *end_line = line;
+                }
+                _ => tokens.push(Spanned {
+                    token: Token::Comment(comment),
+                    line,
+                    end_line: line,
+                    line_comment: true,
+                }),
+            }
+        } else if c == '\''
+            || c == '"'
+            || (c == '@' && (peek(1) == Some('\'') || peek(1) == Some('"')))
+        {
+            let here = c == '@';
+            let quote = if here { chars[pos + 1] } else { c };
+            pos += if here { 2 } else { 1 };
+            let mut s = String::new();
+            while pos < chars.len() {
+                let ch = chars[pos];
+                if ch == '\n' {
+                    line += 1;
+                }
+                if here {
+                    if ch == quote && peek_at(&chars, pos + 1) == Some('@') && s.ends_with('\n') {
+                        pos += 2;
+                        break;
+                    }
+                } else if ch == quote && peek_at(&chars, pos + 1) == Some(quote) {
+                    s.push(quote);
+                    pos += 2;
+                    continue;
+                } else if ch == quote {
+                    pos += 1;
+                    break;
+                } else if ch == '`' && quote == '"' {
+                    if let Some(escaped) = peek_at(&chars, pos + 1) {
+                        s.push(escaped);
+                    }
+                    pos += 2;
+                    continue;
+                }
+                s.push(ch);
+                pos += 1;
+            }
+            tokens.push(Spanned {
+                token: Token::String(s.trim().to_string()),
+                line: start_line,
+                end_line: line,
+                line_comment: false,
+            });
+        } else if "()[]{},;=".contains(c) {
+            pos += 1;
+            tokens.push(Spanned {
+                token: Token::Punct(c),
+                line,
+                end_line: line,
+                line_comment: false,
+            });
+        } else {
+            let start = pos;
+            while pos < chars.len()
+                && !chars[pos].is_whitespace()
+                && !"()[]{},;='\"`".contains(chars[pos])
+            {
+                pos += 1;
+            }
+            tokens.push(Spanned {
+                token: Token::Word(chars[start..pos].iter().collect()),
+                line,
+                end_line: line,
+                line_comment: false,
+            });
+        }
+    }
+
+    tokens
+}
+
+fn peek_at(chars: &[char], pos: usize) -> Option<char> {
+    chars.get(pos).copied()
+}
+
+fn is_word(token: &Spanned, word: &str) -> bool {
+    match &token.token {
+        Token::Word(w) => w.eq_ignore_ascii_case(word),
+        _ => false,
+    }
+}
+
+fn is_punct(token: &Spanned, punct: char) -> bool {
+    token.token == Token::Punct(punct)
+}
+
+/// Index of the bracket closing the one at `open`
+fn matching(tokens: &[Spanned], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (idx, token) in tokens.iter().enumerate().skip(open) {
+        match token.token {
+            Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => depth += 1,
+            Token::Punct(')') | Token::Punct(']') | Token::Punct('}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Split tokens on commas that aren't inside brackets
+fn split_commas(tokens: &[Spanned]) -> Vec<&[Spanned]> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (idx, token) in tokens.iter().enumerate() {
+        match token.token {
+            Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => depth += 1,
+            Token::Punct(')') | Token::Punct(']') | Token::Punct('}') => depth -= 1,
+            Token::Punct(',') if depth == 0 => {
+                parts.push(&tokens[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&tokens[start..]);
+
+    parts
+}
+
+/// Comment-based help, split into sections
+#[derive(Debug, Default)]
+struct CommentHelp {
+    synopsis: String,
+    description: String,
+    parameters: Vec<(String, String)>,
+    examples: Vec<String>,
+    notes: String,
+    links: Vec<String>,
+}
+
+/// Remove the indentation common to all lines, and surrounding blank lines
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+/// Parse comment-based help, returning None for comments that aren't help
+fn parse_comment_help(comment: &str) -> Option<CommentHelp> {
+    let mut sections: Vec<(String, String, Vec<&str>)> = Vec::new();
+    for line in comment.lines() {
+        if let Some(keyword_line) = line.trim().strip_prefix('.') {
+            let mut parts = keyword_line.splitn(2, char::is_whitespace);
+            let keyword = parts.next().unwrap_or("").to_ascii_uppercase();
+            if HELP_KEYWORDS.contains(&keyword.as_str()) {
+                let argument = parts.next().unwrap_or("").trim().to_string();
+                sections.push((keyword, argument, Vec::new()));
+                continue;
+            }
+        }
+        if let Some((_, _, lines)) = sections.last_mut() {
+            lines.push(line);
+        }
+    }
+    if sections.is_empty() {
+        return None;
+    }
+
+    let mut help = CommentHelp::default();
+    for (keyword, argument, lines) in sections {
+        let text = dedent(&lines);
+        match keyword.as_str() {
+            "SYNOPSIS" => help.synopsis = text,
+            "DESCRIPTION" => help.description = text,
+            "PARAMETER" => help.parameters.push((argument, text)),
+            "EXAMPLE" => help.examples.push(text),
+            "NOTES" => help.notes = text,
+            "LINK" => help.links.extend(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(String::from),
+            ),
+            _ => {}
+        }
+    }
+
+    Some(help)
+}
+
+fn comment_help(token: Option<&Spanned>) -> Option<CommentHelp> {
+    match &token?.token {
+        Token::Comment(comment) => parse_comment_help(comment),
+        _ => None,
+    }
+}
+
+/// A parameter declared in a param() block
+struct ScriptParameter {
+    parameter: Parameter,
+    switch: bool,
+
+    /// Parameter set, mandatory and position from each [Parameter()] attribute
+    attributes: Vec<(Option<String>, bool, Option<u32>)>,
+}
+
+fn token_text(token: &Spanned) -> String {
+    match &token.token {
+        Token::Word(s) | Token::String(s) | Token::Comment(s) => s.clone(),
+        Token::Punct(c) => c.to_string(),
+    }
+}
+
+/// Whether an attribute argument is true, eg. Mandatory or Mandatory = $true
+fn attribute_flag(argument: &[Spanned]) -> bool {
+    match argument.get(2) {
+        Some(value) => !is_word(value, "$false") && !is_word(value, "0"),
+        None => true,
+    }
+}
+
+/// Names given to an [Alias()] attribute
+fn alias_arguments(arguments: &[Spanned]) -> Vec<String> {
+    arguments
+        .iter()
+        .filter_map(|t| match &t.token {
+            Token::String(s) | Token::Word(s) => Some(s.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_parameter(tokens: &[Spanned]) -> Option<ScriptParameter> {
+    let mut type_name = None;
+    let mut attributes = Vec::new();
+    let mut aliases = Vec::new();
+    let mut comments = Vec::new();
+    let mut pipeline_by_value = false;
+    let mut pipeline_by_property_name = false;
+
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = &tokens[idx];
+        match &token.token {
+            Token::Punct('[') => {
+                let end = matching(tokens, idx)?;
+                let inner = &tokens[idx + 1..end];
+                idx = end + 1;
+
+                let is_call = inner.get(1).map(|t| is_punct(t, '(')).unwrap_or(false);
+                if !is_call {
+                    type_name = Some(inner.iter().map(token_text).collect::<String>());
+                    continue;
+                }
+                let arguments = &inner[2..inner.len().saturating_sub(1)];
+                if is_word(&inner[0], "Parameter") {
+                    let mut set = None;
+                    let mut mandatory = false;
+                    let mut position = None;
+                    for argument in split_commas(arguments) {
+                        let name = match argument.first() {
+                            Some(name) => token_text(name).to_ascii_lowercase(),
+                            None => continue,
+                        };
+                        let value = argument.get(2).map(token_text);
+                        match name.as_str() {
+                            "mandatory" => mandatory = attribute_flag(argument),
+                            "position" => position = value.and_then(|v| v.parse().ok()),
+                            "parametersetname" => set = value,
+                            "valuefrompipeline" => pipeline_by_value |= attribute_flag(argument),
+                            "valuefrompipelinebypropertyname" => {
+                                pipeline_by_property_name |= attribute_flag(argument)
+                            }
+                            _ => {}
+                        }
+                    }
+                    attributes.push((set, mandatory, position));
+                } else if is_word(&inner[0], "Alias") {
+                    aliases.extend(alias_arguments(arguments));
+                }
+            }
+            Token::Comment(comment) => {
+                comments.push(comment.trim().to_string());
+                idx += 1;
+            }
+            Token::Word(word) if word.starts_with('$') => {
+                let name = word[1..].to_string();
+                let type_name = type_name.unwrap_or_default();
+                let switch = type_name.eq_ignore_ascii_case("switch")
+                    || type_name.ends_with("SwitchParameter");
+                let parameter_sets = attributes
+                    .iter()
+                    .filter_map(|(set, _, _): &(Option<String>, bool, Option<u32>)| set.clone())
+                    .filter(|s| !s.eq_ignore_ascii_case("__AllParameterSets"))
+                    .collect();
+
+                return Some(ScriptParameter {
+                    parameter: Parameter {
+                        name,
+                        type_name,
+                        aliases,
+                        mandatory: attributes.iter().any(|(_, mandatory, _)| *mandatory),
+                        pipeline_by_value,
+                        pipeline_by_property_name,
+                        parameter_sets,
+                        description: comments.join("\n"),
+                    },
+                    switch,
+                    attributes,
+                });
+            }
+            _ => idx += 1,
+        }
+    }
+
+    None
+}
+
+/// Syntax lines for each parameter set, like Get-Help shows
+fn build_syntax(name: &str, parameters: &[ScriptParameter], advanced: bool) -> String {
+    let mut sets: Vec<&str> = Vec::new();
+    for set in parameters
+        .iter()
+        .flat_map(|p| p.parameter.parameter_sets.iter())
+    {
+        if !sets.iter().any(|s| s.eq_ignore_ascii_case(set)) {
+            sets.push(set);
+        }
+    }
+
+    // Without explicit positions, parameters are positional in the order
+    // they're declared
+    let explicit_positions = parameters.iter().any(|p| {
+        p.attributes
+            .iter()
+            .any(|(_, _, position)| position.is_some())
+    });
+    let mut implicit_position = 0;
+    let mut implicit_positions = Vec::new();
+    for param in parameters {
+        if explicit_positions || param.switch {
+            implicit_positions.push(None);
+        } else {
+            implicit_positions.push(Some(implicit_position));
+            implicit_position += 1;
+        }
+    }
+
+    let set_syntax = |set: Option<&str>| {
+        let parameters = parameters
+            .iter()
+            .zip(&implicit_positions)
+            .filter_map(|(param, implicit_position)| {
+                let attribute = param.attributes.iter().find(|(s, _, _)| match (s, set) {
+                    (Some(s), Some(set)) => s.eq_ignore_ascii_case(set),
+                    (None, _) => true,
+                    (Some(s), None) => s.eq_ignore_ascii_case("__AllParameterSets"),
+                });
+                if set.is_some()
+                    && !param.parameter.parameter_sets.is_empty()
+                    && attribute.is_none()
+                {
+                    return None;
+                }
+                let (mandatory, position) = attribute
+                    .map(|(_, mandatory, position)| (*mandatory, *position))
+                    .unwrap_or((false, None));
+
+                Some(SyntaxParameter {
+                    name: param.parameter.name.clone(),
+                    type_name: Some(if param.parameter.type_name.is_empty() {
+                        "Object".to_string()
+                    } else {
+                        param.parameter.type_name.clone()
+                    }),
+                    mandatory,
+                    position: position.or(*implicit_position),
+                    switch: param.switch,
+                })
+            })
+            .collect();
+
+        ParameterSetSyntax {
+            command: name.to_string(),
+            parameters,
+            common_parameters: advanced,
+        }
+        .to_string()
+    };
+
+    if sets.is_empty() {
+        set_syntax(None)
+    } else {
+        sets.into_iter()
+            .map(|set| set_syntax(Some(set)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn build_examples(help: &CommentHelp) -> Vec<Example> {
+    help.examples
+        .iter()
+        .enumerate()
+        .map(|(idx, example)| {
+            // The code comes first, then remarks after a blank line
+            let (code, remarks) = match example.find("\n\n") {
+                Some(split) => (&example[..split], example[split..].trim()),
+                None => (example.as_str(), ""),
+            };
+
+            Example {
+                title: format!("Example {}", idx + 1),
+                code: code.trim().to_string(),
+                remarks: remarks.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn process_function(
+    tokens: &[Spanned],
+    idx: usize,
+    module: &str,
+    module_version: &str,
+) -> Option<Cmdlet> {
+    let name = match &tokens.get(idx + 1)?.token {
+        Token::Word(name) => name,
+        _ => return None,
+    };
+    // Drop any scope, eg. function global:Get-Thing
+    let name = name.rsplit(':').next().unwrap_or(name).to_string();
+
+    let mut body_start = idx + 2;
+    let mut param_block = None;
+    if is_punct(tokens.get(body_start)?, '(') {
+        let end = matching(tokens, body_start)?;
+        param_block = Some((body_start + 1, end));
+        body_start = end + 1;
+    }
+    if !is_punct(tokens.get(body_start)?, '{') {
+        return None;
+    }
+    let body_end = matching(tokens, body_start)?;
+    let body = &tokens[body_start + 1..body_end];
+
+    // Look for [CmdletBinding()], [Alias()] and param() at the top level of
+    // the body
+    let mut advanced = false;
+    let mut aliases = Vec::new();
+    let mut body_idx = 0;
+    while body_idx < body.len() {
+        let token = &body[body_idx];
+        if is_punct(token, '[') {
+            let end = matching(body, body_idx)?;
+            let inner = &body[body_idx + 1..end];
+            advanced |= inner
+                .first()
+                .map(|t| is_word(t, "CmdletBinding"))
+                .unwrap_or(false);
+            let is_alias = inner.first().map(|t| is_word(t, "Alias")).unwrap_or(false)
+                && inner.get(1).map(|t| is_punct(t, '(')).unwrap_or(false);
+            if is_alias {
+                aliases.extend(alias_arguments(&inner[2..inner.len().saturating_sub(1)]));
+            }
+        }
+        if param_block.is_none()
+            && is_word(token, "param")
+            && body
+                .get(body_idx + 1)
+                .map(|t| is_punct(t, '('))
+                .unwrap_or(false)
+        {
+            let end = matching(body, body_idx + 1)?;
+            param_block = Some((body_start + 1 + body_idx + 2, body_start + 1 + end));
+            break;
+        }
+        body_idx = match token.token {
+            Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => {
+                matching(body, body_idx)? + 1
+            }
+            _ => body_idx + 1,
+        };
+    }
+
+    let mut parameters = param_block
+        .map(|(start, end)| {
+            split_commas(&tokens[start..end])
+                .into_iter()
+                .filter_map(parse_parameter)
+                .filter(|p| !is_common_parameter(&p.parameter.name))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    advanced |= parameters.iter().any(|p| !p.attributes.is_empty());
+
+    // Help can be just before the function, or at the start or end of its body
+    let before = idx
+        .checked_sub(1)
+        .map(|i| &tokens[i])
+        .filter(|t| t.end_line + 2 >= tokens[idx].line);
+    let help = comment_help(before)
+        .or_else(|| comment_help(body.first()))
+        .or_else(|| comment_help(body.last()))
+        .unwrap_or_default();
+
+    for param in &mut parameters {
+        let description = help
+            .parameters
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&param.parameter.name));
+        if let Some((_, description)) = description {
+            param.parameter.description = description.clone();
+        }
+    }
+
+    let links = help
+        .links
+        .iter()
+        .map(|link| {
+            if link.starts_with("http://") || link.starts_with("https://") {
+                Link {
+                    title: String::new(),
+                    uri: link.clone(),
+                }
+            } else {
+                Link {
+                    title: link.clone(),
+                    uri: String::new(),
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let url = links
+        .iter()
+        .map(|l| l.uri.clone())
+        .find(|uri| !uri.is_empty())
+        .unwrap_or_default();
+
+    Some(Cmdlet {
+        module: module.to_string(),
+        module_version: module_version.to_string(),
+        syntax: build_syntax(&name, &parameters, advanced),
+        synopsis: help.synopsis.clone(),
+        description: help.description.clone(),
+        notes: help.notes.clone(),
+        examples: build_examples(&help),
+        parameters: parameters.into_iter().map(|p| p.parameter).collect(),
+        links,
+        url,
+        name,
+        aliases,
+        tags: Vec::new(),
+        module_info: Default::default(),
+        // Script modules come from the gallery, callers know better
+        source: Source::PSGallery,
+    })
+}
+
+/// Find the functions defined in a script, with their parameters and
+/// comment-based help
+pub fn process_script(text: &str, module: &str, module_version: &str) -> Vec<Cmdlet> {
+    let tokens = tokenize(text);
+
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| is_word(t, "function") || is_word(t, "filter"))
+        .filter_map(|(idx, _)| process_function(&tokens, idx, module, module_version))
+        .collect()
+}
+
+/// Parameters of Set-Alias and New-Alias which take a value
+const ALIAS_PARAMETERS: &[&str] = &["-name", "-value", "-scope", "-option", "-description"];
+
+/// Aliases a script defines with Set-Alias or New-Alias, as (alias, command)
+pub fn script_aliases(text: &str) -> Vec<(String, String)> {
+    let tokens = tokenize(text);
+
+    let mut aliases = Vec::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        if !is_word(token, "Set-Alias") && !is_word(token, "New-Alias") {
+            continue;
+        }
+
+        // Arguments run to the end of the line or statement
+        let mut arguments = tokens[idx + 1..].iter().take_while(|t| {
+            t.line == token.line
+                && !is_word(t, "|")
+                && !matches!(t.token, Token::Punct(_) | Token::Comment(_))
+        });
+        let mut name = None;
+        let mut value = None;
+        let mut positional = Vec::new();
+        while let Some(argument) = arguments.next() {
+            match &argument.token {
+                Token::Word(w) if w.starts_with('-') => {
+                    let parameter = w.to_ascii_lowercase();
+                    if ALIAS_PARAMETERS.contains(&parameter.as_str()) {
+                        let argument = arguments.next().map(token_text);
+                        match parameter.as_str() {
+                            "-name" => name = argument,
+                            "-value" => value = argument,
+                            _ => {}
+                        }
+                    }
+                }
+                _ => positional.push(token_text(argument)),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let name = name.or_else(|| positional.next());
+        let value = value.or_else(|| positional.next());
+        if let (Some(name), Some(value)) = (name, value) {
+            // Aliases built from variables can't be resolved
+            if !name.is_empty() && !value.is_empty() && !name.contains('$') && !value.contains('$')
+            {
+                aliases.push((name, value));
+            }
+        }
+    }
+
+    aliases
+}
+
+/// Add the aliases, as (alias, command) pairs, which resolve to a cmdlet
+pub fn add_aliases(cmdlet: &mut Cmdlet, aliases: &[(String, String)]) {
+    for (alias, command) in aliases {
+        if command.eq_ignore_ascii_case(&cmdlet.name)
+            && !cmdlet.aliases.iter().any(|a| a.eq_ignore_ascii_case(alias))
+        {
+            cmdlet.aliases.push(alias.clone());
+        }
+    }
+}
+
+pub fn is_script_file(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".psm1") || name.ends_with(".ps1")
+}
+
+/// Directories of scripts that ship with a module without being part of it
+const NON_MODULE_DIRS: &[&str] = &["test", "tests", "build", "examples", "samples"];
+
+/// Scripts that build a module rather than being part of it
+const NON_MODULE_SCRIPTS: &[&str] = &["build.ps1", "psakefile.ps1"];
+
+/// Whether a script, given its path in the module, is part of the module
+/// rather than its tests or build, which define stubs and helpers
+pub fn is_module_script(path: &str) -> bool {
+    let path = path.replace('\\', "/").to_ascii_lowercase();
+    let mut parts = path.split('/').collect::<Vec<_>>();
+    let file_name = parts.pop().unwrap_or("");
+
+    is_script_file(file_name)
+        && !file_name.ends_with(".tests.ps1")
+        && !file_name.ends_with(".build.ps1")
+        && !NON_MODULE_SCRIPTS.contains(&file_name)
+        && !parts.iter().any(|d| NON_MODULE_DIRS.contains(d))
+}
+
+/// Functions a script exports with Export-ModuleMember, as names or
+/// wildcards, or None if it doesn't call it
+fn module_members(text: &str) -> Option<Vec<String>> {
+    let tokens = tokenize(text);
+
+    let mut members = None;
+    for (idx, token) in tokens.iter().enumerate() {
+        if !is_word(token, "Export-ModuleMember") {
+            continue;
+        }
+        let functions = members.get_or_insert_with(Vec::new);
+
+        // Arguments run to the end of the statement, which continues after a
+        // comma or inside brackets
+        let mut depth = 0;
+        // Depth of an expression, eg. (Get-ChildItem), being skipped
+        let mut expression: Option<usize> = None;
+        let mut function_argument = true;
+        let mut previous = token;
+        for argument in &tokens[idx + 1..] {
+            let continued = depth > 0 || is_punct(previous, ',');
+            if (argument.line != previous.end_line && !continued)
+                || (depth == 0 && (is_punct(argument, ';') || is_word(argument, "|")))
+                || matches!(argument.token, Token::Comment(_))
+            {
+                break;
+            }
+            let is_array = is_word(previous, "@");
+            previous = argument;
+
+            match &argument.token {
+                Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => {
+                    if expression.is_none() && !is_array {
+                        // Exports built at run time, eg. from file names,
+                        // can't be resolved, so assume they're the functions
+                        // named like commands
+                        if function_argument {
+                            functions.push("*-*".to_string());
+                        }
+                        expression = Some(depth);
+                    }
+                    depth += 1;
+                }
+                Token::Punct(')') | Token::Punct(']') | Token::Punct('}') => {
+                    depth -= 1;
+                    if expression == Some(depth) {
+                        expression = None;
+                    }
+                }
+                _ if expression.is_some() => {}
+                Token::Word(w) if w.starts_with('-') => {
+                    let parameter = w.to_ascii_lowercase();
+                    // Other members, eg. -Alias, or -Function abbreviated
+                    function_argument = parameter.len() > 1 && "-function".starts_with(&parameter);
+                }
+                Token::Word(w) if w == "@" => {}
+                Token::Word(w) | Token::String(w) if function_argument => {
+                    // Variables can't be resolved either
+                    if w.contains('$') {
+                        functions.push("*-*".to_string());
+                    } else {
+                        functions.push(w.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    members
+}
+
+/// Only keep functions the module exports: those matching FunctionsToExport,
+/// which may be wildcards, and anything passed to Export-ModuleMember. Without
+/// either, keep anything named like a command, rather than helpers.
+fn exported(
+    cmdlets: Vec<Cmdlet>,
+    functions: &[String],
+    module_members: Option<&[String]>,
+) -> Vec<Cmdlet> {
+    let matches =
+        |patterns: &[String], name: &str| patterns.iter().any(|p| wildcard_match(p, name));
+
+    cmdlets
+        .into_iter()
+        .filter(|c| {
+            let by_manifest = if functions.is_empty() {
+                module_members.is_some() || c.name.contains('-')
+            } else {
+                matches(functions, &c.name)
+            };
+            by_manifest && module_members.map(|m| matches(m, &c.name)).unwrap_or(true)
+        })
+        .collect()
+}
+
+/// Add a function unless it's already been found, replacing a definition
+/// without help, eg. a stub, with one that has it
+fn add_function(functions: &mut Vec<Cmdlet>, function: Cmdlet) {
+    let has_help = |c: &Cmdlet| !c.synopsis.is_empty() || !c.description.is_empty();
+    match functions
+        .iter_mut()
+        .find(|f| f.name.eq_ignore_ascii_case(&function.name))
+    {
+        Some(existing) => {
+            if !has_help(existing) && has_help(&function) {
+                *existing = function;
+            }
+        }
+        None => functions.push(function),
+    }
+}
+
+/// Find the functions a module's scripts define and export, given the
+/// manifest's FunctionsToExport, along with the aliases the scripts define as
+/// (alias, command) pairs
+pub fn process_module_scripts(
+    scripts: &[String],
+    module: &str,
+    module_version: &str,
+    functions: &[String],
+) -> (Vec<Cmdlet>, Vec<(String, String)>) {
+    let mut cmdlets = Vec::new();
+    let mut aliases = Vec::new();
+    let mut members: Option<Vec<String>> = None;
+    for text in scripts {
+        for function in process_script(text, module, module_version) {
+            add_function(&mut cmdlets, function);
+        }
+        aliases.extend(script_aliases(text));
+        if let Some(exports) = module_members(text) {
+            members.get_or_insert_with(Vec::new).extend(exports);
+        }
+    }
+
+    (exported(cmdlets, functions, members.as_deref()), aliases)
+}
+
+/// Find the exported functions in a module directory's scripts, with any
+/// aliases the scripts define for them
+pub fn process_module_dir(
+    dir: &Path,
+    module: &str,
+    module_version: &str,
+    functions: &[String],
+) -> Vec<Cmdlet> {
+    let scripts = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|de| {
+            let path = de.path().strip_prefix(dir).unwrap_or_else(|_| de.path());
+            de.file_type().is_file() && is_module_script(&path.to_string_lossy())
+        })
+        .filter_map(|de| match std::fs::read(de.path()) {
+            Ok(bytes) => Some(decode_text(&bytes)),
+            Err(e) => {
+                log::warn!("{}: {:?}", de.path().display(), e);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let (mut cmdlets, aliases) =
+        process_module_scripts(&scripts, module, module_version, functions);
+    for cmdlet in &mut cmdlets {
+        add_aliases(cmdlet, &aliases);
+    }
+
+    cmdlets
+}
+
+#[test]
+fn script_functions() {
+    let script = r#"
+$braces = "function Not-AFunction { '}' }"
+
+<#
+.SYNOPSIS
+    Gets things.
+
+.DESCRIPTION
+    The Get-Thing function gets things,
+    quickly.
+
+.PARAMETER Name
+    Names of the things to get.
+
+.EXAMPLE
+    Get-Thing -Name one
+
+    Gets the thing called one.
+
+.LINK
+    https://example.com/get-thing
+.LINK
+    Set-Thing
+#>
+function Get-Thing {
+    [CmdletBinding(DefaultParameterSetName = 'Name')]
+    [Alias('gth')]
+    param(
+        [Parameter(Mandatory, Position = 0, ValueFromPipeline = $true, ParameterSetName = 'Name')]
+        [Alias('n')]
+        [ValidateNotNullOrEmpty()]
+        [string[]] $Name,
+
+        [Parameter(Mandatory = $true, ParameterSetName = 'Id')]
+        [int] $Id,
+
+        # Get things even if they're hidden
+        [switch] $Force
+    )
+
+    process { $Name | ForEach-Object { "{$_}" } }
+}
+
+function global:Set-Thing($Thing, [switch]$PassThru) {
+    # .SYNOPSIS
+    # Sets things.
+    Write-Output $Thing
+}
+
+function helper { }
+
+Set-Alias -Name sth -Value Set-Thing -Scope Global
+New-Alias gt Get-Thing; Set-Alias -Value helper -Name $name
+"#;
+
+    let mut cmdlets = process_script(script, "Things", "1.0.0");
+    let aliases = script_aliases(script);
+    assert_eq!(
+        aliases,
+        vec![
+            ("sth".to_string(), "Set-Thing".to_string()),
+            ("gt".to_string(), "Get-Thing".to_string())
+        ]
+    );
+    for cmdlet in &mut cmdlets {
+        add_aliases(cmdlet, &aliases);
+    }
+    let names = cmdlets.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["Get-Thing", "Set-Thing", "helper"]);
+
+    let get = &cmdlets[0];
+    assert_eq!(get.synopsis, "Gets things.");
+    assert_eq!(
+        get.description,
+        "The Get-Thing function gets things,\nquickly."
+    );
+    assert_eq!(
+        get.syntax,
+        "Get-Thing [-Name] <string[]> [-Force] [<CommonParameters>]\n\
+         Get-Thing -Id <int> [-Force] [<CommonParameters>]"
+    );
+    assert_eq!(get.aliases, vec!["gth", "gt"]);
+    assert_eq!(get.url, "https://example.com/get-thing");
+    assert_eq!(get.links[1].title, "Set-Thing");
+    assert_eq!(get.examples[0].code, "Get-Thing -Name one");
+    assert_eq!(get.examples[0].remarks, "Gets the thing called one.");
+    let name = &get.parameters[0];
+    assert_eq!(name.type_name, "string[]");
+    assert_eq!(name.aliases, vec!["n"]);
+    assert_eq!(name.description, "Names of the things to get.");
+    assert!(name.mandatory && name.pipeline_by_value);
+    assert_eq!(name.parameter_sets, vec!["Name"]);
+    assert_eq!(
+        get.parameters[2].description,
+        "Get things even if they're hidden"
+    );
+
+    let set = &cmdlets[1];
+    assert_eq!(set.synopsis, "Sets things.");
+    assert_eq!(set.aliases, vec!["sth"]);
+    assert_eq!(set.syntax, "Set-Thing [[-Thing] <Object>] [-PassThru]");
+
+    assert_eq!(
+        exported(cmdlets, &[], None)
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Get-Thing", "Set-Thing"]
+    );
+}
+
+#[test]
+fn module_scripts() {
+    let stubs = "function Get-Thing { }\nfunction helper { }\n".to_string();
+    let module = r#"
+<#
+.SYNOPSIS
+    Gets things.
+#>
+function Get-Thing { }
+function Set-Thing { }
+function Remove-Thing { }
+Export-ModuleMember -Function 'Get-Thing',
+    'Set-Thing' -Alias gth
+"#
+    .to_string();
+    let dynamic =
+        "function Get-Thing { }\nExport-ModuleMember -Function $public.BaseName\n".to_string();
+    let names = |scripts: &[String], functions: &[&str]| {
+        let functions = functions.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        let (cmdlets, _) = process_module_scripts(scripts, "Things", "1.0.0", &functions);
+        cmdlets
+            .into_iter()
+            .map(|c| format!("{} {}", c.name, c.synopsis).trim().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // The definition with help wins over a stub, and Export-ModuleMember
+    // narrows down FunctionsToExport = '*'
+    assert_eq!(
+        names(&[stubs.clone(), module.clone()], &["*"]),
+        vec!["Get-Thing Gets things.", "Set-Thing"]
+    );
+    assert_eq!(
+        names(&[stubs.clone(), module], &["Get-*"]),
+        vec!["Get-Thing Gets things."]
+    );
+    assert_eq!(names(&[stubs, dynamic], &["*"]), vec!["Get-Thing"]);
+
+    assert!(is_module_script("Things.psm1"));
+    assert!(is_module_script("Public/Get-Thing.ps1"));
+    assert!(!is_module_script("Get-Thing.Tests.ps1"));
+    assert!(!is_module_script("tests\\helpers.ps1"));
+    assert!(!is_module_script("build.ps1"));
+}
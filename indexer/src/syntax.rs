@@ -0,0 +1,176 @@
+use std::fmt::{self, Display};
+
+/// Parameters added to every advanced function/cmdlet, shown as `[<CommonParameters>]`
+const COMMON_PARAMETERS: &[&str] = &[
+    "Debug",
+    "ErrorAction",
+    "ErrorVariable",
+    "InformationAction",
+    "InformationVariable",
+    "OutBuffer",
+    "OutVariable",
+    "PipelineVariable",
+    "ProgressAction",
+    "Verbose",
+    "WarningAction",
+    "WarningVariable",
+];
+
+pub fn is_common_parameter(name: &str) -> bool {
+    COMMON_PARAMETERS
+        .iter()
+        .any(|p| p.eq_ignore_ascii_case(name))
+}
+
+/// A single parameter as it appears in a syntax line
+pub struct SyntaxParameter {
+    /// Parameter name, without the leading dash
+    pub name: String,
+
+    /// Type name of the parameter, eg. System.String[]
+    pub type_name: Option<String>,
+
+    /// Whether the parameter is mandatory in this parameter set
+    pub mandatory: bool,
+
+    /// Position of the parameter, if it can be passed positionally
+    pub position: Option<u32>,
+
+    /// Whether the parameter is a switch
+    pub switch: bool,
+}
+
+/// A parameter set, rendered as one line of PowerShell style syntax
+pub struct ParameterSetSyntax {
+    /// Name of the command the parameter set belongs to
+    pub command: String,
+
+    /// Parameters in declaration order
+    pub parameters: Vec<SyntaxParameter>,
+
+    /// Whether the command supports common parameters
+    pub common_parameters: bool,
+}
+
+/// Shorten a .NET type name to the form PowerShell uses in syntax, eg.
+/// System.String[] becomes string[]
+pub fn short_type_name(type_name: &str) -> String {
+    let type_name = type_name.trim();
+    let (base, suffix) = match type_name.find('[') {
+        Some(idx) => type_name.split_at(idx),
+        None => (type_name, ""),
+    };
+    let base = base.rsplit('.').next().unwrap_or(base);
+    let base = match base {
+        "String" => "string",
+        "Char" => "char",
+        "Byte" => "byte",
+        "Int32" => "int",
+        "Int64" => "long",
+        "UInt32" => "uint32",
+        "UInt64" => "uint64",
+        "Boolean" => "bool",
+        "Double" => "double",
+        "Single" => "float",
+        "Decimal" => "decimal",
+        "Object" => "Object",
+        "Hashtable" => "hashtable",
+        "ScriptBlock" => "scriptblock",
+        "PSCredential" => "pscredential",
+        "PSObject" => "psobject",
+        "SwitchParameter" => "switch",
+        other => other,
+    };
+
+    format!("{}{}", base, suffix)
+}
+
+impl Display for SyntaxParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = if self.position.is_some() && !self.switch {
+            format!("[-{}]", self.name)
+        } else {
+            format!("-{}", self.name)
+        };
+        let param = match &self.type_name {
+            Some(type_name) if !self.switch => {
+                format!("{} <{}>", name, short_type_name(type_name))
+            }
+            _ => name,
+        };
+
+        if self.mandatory {
+            f.write_str(&param)
+        } else {
+            f.write_fmt(format_args!("[{}]", param))
+        }
+    }
+}
+
+impl Display for ParameterSetSyntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // PowerShell lists positional parameters first, then the rest in
+        // declaration order
+        let mut positional = self
+            .parameters
+            .iter()
+            .filter(|p| p.position.is_some())
+            .collect::<Vec<_>>();
+        positional.sort_by_key(|p| p.position);
+        let named = self.parameters.iter().filter(|p| p.position.is_none());
+
+        f.write_str(&self.command)?;
+        for param in positional.into_iter().chain(named) {
+            f.write_fmt(format_args!(" {}", param))?;
+        }
+        if self.common_parameters {
+            f.write_str(" [<CommonParameters>]")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn parameter_set_syntax() {
+    let syntax = ParameterSetSyntax {
+        command: "Get-Thing".to_string(),
+        parameters: vec![
+            SyntaxParameter {
+                name: "Force".to_string(),
+                type_name: Some("System.Management.Automation.SwitchParameter".to_string()),
+                mandatory: false,
+                position: None,
+                switch: true,
+            },
+            SyntaxParameter {
+                name: "Credential".to_string(),
+                type_name: Some("System.Management.Automation.PSCredential".to_string()),
+                mandatory: true,
+                position: None,
+                switch: false,
+            },
+            SyntaxParameter {
+                name: "Path".to_string(),
+                type_name: Some("System.String[]".to_string()),
+                mandatory: false,
+                position: Some(1),
+                switch: false,
+            },
+            SyntaxParameter {
+                name: "Name".to_string(),
+                type_name: Some("System.String".to_string()),
+                mandatory: true,
+                position: Some(0),
+                switch: false,
+            },
+        ],
+        common_parameters: true,
+    };
+
+    assert_eq!(
+        syntax.to_string(),
+        "Get-Thing [-Name] <string> [[-Path] <string[]>] [-Force] \
+         -Credential <pscredential> [<CommonParameters>]"
+    );
+}
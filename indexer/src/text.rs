@@ -0,0 +1,30 @@
+/// Decode a text file using its byte order mark. Help files and manifests
+/// written by Windows PowerShell are often UTF-16.
+pub fn decode_text(bytes: &[u8]) -> String {
+    let utf16 = |from_bytes: fn([u8; 2]) -> u16| {
+        let units = bytes[2..]
+            .chunks(2)
+            .filter(|c| c.len() == 2)
+            .map(|c| from_bytes([c[0], c[1]]))
+            .collect::<Vec<_>>();
+        String::from_utf16_lossy(&units)
+    };
+
+    if bytes.starts_with(&[0xff, 0xfe]) {
+        utf16(u16::from_le_bytes)
+    } else if bytes.starts_with(&[0xfe, 0xff]) {
+        utf16(u16::from_be_bytes)
+    } else {
+        String::from_utf8_lossy(bytes)
+            .trim_start_matches('\u{feff}')
+            .to_string()
+    }
+}
+
+#[test]
+fn decode_utf16() {
+    let mut bytes = vec![0xff, 0xfe];
+    bytes.extend("<helpItems/>".encode_utf16().flat_map(u16::to_le_bytes));
+    assert_eq!(decode_text(&bytes), "<helpItems/>");
+    assert_eq!(decode_text(b"\xef\xbb\xbf<helpItems/>"), "<helpItems/>");
+}
@@ -57,6 +57,7 @@ foreach ($snapin in $snapins)
         [System.Management.Automation.PSCmdlet].IsAssignableFrom($_.BaseType)
     }
 
+    $aliases = @()
     foreach ($command in $commands)
     {
         $cmdletAttr = $command.GetCustomAttributes([System.Management.Automation.CmdletAttribute], $true)
@@ -77,5 +78,11 @@ foreach ($snapin in $snapins)
         $helpJson = Get-Help -Full $commandName | ConvertTo-Json
         $helpPath = Join-Path -Path $helpDir -ChildPath $jsonName
         [System.IO.File]::WriteAllLines($helpPath, $helpJson)
+
+        $aliases += Get-Alias -Definition $commandName -ErrorAction SilentlyContinue
     }
+
+    $aliasJson = ConvertTo-Json -InputObject @($aliases | Select-Object -Property Name, CommandType, Definition)
+    $aliasPath = Join-Path -Path $docDir -ChildPath "aliases.json"
+    [System.IO.File]::WriteAllLines($aliasPath, $aliasJson)
 }